@@ -7,10 +7,10 @@ async fn main() {
     let mut server = Server::<i32, i32>::bind(IP).await.unwrap();
     let mut client = Client::connect(IP).await.unwrap();
 
-    client.emit_request(15).await;
+    client.emit_request(15).await.unwrap();
 
-    let (req, origin) = server.get_request().await;
-    server.emit_response(req + 4, origin.into()).await;
+    let (req, origin) = server.get_request().await.unwrap();
+    server.emit_response(req + 4, origin.into()).await.unwrap();
 
     let res: i32 = client.get_response().await.unwrap();
 
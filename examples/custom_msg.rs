@@ -10,27 +10,25 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     });
 
     loop{
-        let (req, o) = server.get_request().await;
+        let (req, o) = server.get_request().await?;
         if req.username == "Ferris" && req.password == "[rab$Rav3" {
-            server.emit_response(Correct::Yes, o.into()).await;
+            server.emit_response(Correct::Yes, o.into()).await?;
         } else {
-            server.emit_response(Correct::No,  o.into()).await;
+            server.emit_response(Correct::No,  o.into()).await?;
         }
     }
 }
 
 async fn client() {
-    let mut client = Client::<Login, Correct>::connect(IP).await.unwrap();
+    let client = Client::<Login, Correct>::connect(IP).await.unwrap();
 
-    // simulate some login attempts
+    // simulate some login attempts, every call waits for its own response
     let req = Login{username: "Ferris".to_string(), password: "crabrave".to_string()};
-    client.emit_request(req).await;
-    let res = client.get_response().await.unwrap();
+    let res = client.call(req).await.unwrap();
     println!("first: {:?}", res);
 
     let req = Login{username: "Ferris".to_string(), password: "[rab$Rav3".to_string()};
-    client.emit_request(req).await;
-    let res = client.get_response().await.unwrap();
+    let res = client.call(req).await.unwrap();
     println!("second: {:?}", res);
 }
 
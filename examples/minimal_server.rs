@@ -5,9 +5,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let mut server = Server::<String, String>::bind("[::1]:50052").await?;
     
     loop{
-        let (req, origin) = server.get_request().await;
+        let (req, origin) = server.get_request().await?;
 
         let msg = format!("Hello {}! Happy to see you here!", req);
-        server.emit_response(msg, origin.into()).await;
+        server.emit_response(msg, origin.into()).await?;
     }
 }
\ No newline at end of file
@@ -0,0 +1,53 @@
+use std::{io, net::SocketAddr};
+#[cfg(unix)]
+use std::path::PathBuf;
+
+use tokio::net::TcpStream;
+#[cfg(unix)]
+use tokio::net::UnixStream;
+#[cfg(feature = "tls")]
+use tokio_rustls::{TlsConnector, rustls::pki_types::ServerName};
+
+use crate::instance;
+
+/// How a Client connects to its Server. Kept around to connect again.
+#[derive(Clone)]
+pub(crate) enum Dial{
+    /// Resolved once, so a reconnect doesnt depend on DNS.
+    Tcp(Vec<SocketAddr>),
+    #[cfg(feature = "tls")]
+    Tls(Vec<SocketAddr>, ServerName<'static>, TlsConnector),
+    #[cfg(feature = "websocket")]
+    WebSocket(String),
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+impl Dial {
+    pub async fn connect(&self) -> io::Result<(instance::ReadHalf, instance::WriteHalf)> {
+        match self {
+            Dial::Tcp(addrs) => {
+                let (read, write) = TcpStream::connect(&addrs[..]).await?.into_split();
+                Ok((Box::new(read), Box::new(write)))
+            },
+            #[cfg(feature = "tls")]
+            Dial::Tls(addrs, domain, connector) => {
+                let stream = TcpStream::connect(&addrs[..]).await?;
+                let stream = connector.connect(domain.clone(), stream).await?;
+                let (read, write) = tokio::io::split(stream);
+                Ok((Box::new(read), Box::new(write)))
+            },
+            #[cfg(feature = "websocket")]
+            Dial::WebSocket(url) => {
+                let (stream, _) = tokio_tungstenite::connect_async(url.as_str()).await.map_err(io::Error::other)?;
+                let (read, write) = tokio::io::split(instance::WsStream::new(stream));
+                Ok((Box::new(read), Box::new(write)))
+            },
+            #[cfg(unix)]
+            Dial::Unix(path) => {
+                let (read, write) = UnixStream::connect(path).await?.into_split();
+                Ok((Box::new(read), Box::new(write)))
+            },
+        }
+    }
+}
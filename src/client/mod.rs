@@ -1,39 +1,151 @@
 //! Module for Client functionality. Enable the client feature to use it.
 
-use std::{io, time::Duration};
-use tokio::{net::{ToSocketAddrs, TcpStream}, sync::mpsc};
-use crate::{Message, instance, event::{Origin, Event}};
+use std::{io, fmt, error, collections::HashSet, time::Duration, marker::PhantomData, sync::Arc};
+#[cfg(unix)]
+use std::path::Path;
+use tokio::{net::ToSocketAddrs, sync::mpsc};
+#[cfg(feature = "tls")]
+use tokio_rustls::{TlsConnector, rustls::{ClientConfig, pki_types::ServerName}};
+use crate::{Message, Error, Result, instance::{self, Calls, CallError, Outgoing, queue}, event::{Origin, Event, Illegal}, transport::{Stream, FlushPolicy}, codec::{Codec, Bincode}};
+
+mod dial;
+mod reconnect;
+use dial::Dial;
+pub use reconnect::Reconnect;
+use reconnect::Supervisor;
 
 pub use tokio::sync::mpsc::error::TryRecvError;
 
 #[derive(Debug)]
 /// A Client with a full duplex connection to a Server. Can be .into_split()
 /// into an Emitter and Collector for async operations.
-pub struct Client<Req: Message, Res: Message>{
+/// 
+/// The `Codec` is picked through the Config and defaults to `Bincode`.
+pub struct Client<Req: Message, Res: Message, C = Bincode>{
     collector: Collector<Res>,
-    emitter: Emitter<Req>,
+    emitter: Emitter<Req, Res>,
+    codec: PhantomData<C>,
 }
 
-impl<Req: Message, Res: Message> Client<Req, Res>{
+impl<Req: Message, Res: Message> Client<Req, Res> where Bincode: Codec<Req> + Codec<Res> {
     /// Connects to the server with the default Config.
     pub async fn connect<A: ToSocketAddrs>(ip: A) -> io::Result<Client<Req, Res>> {
         Self::connect_with_config(ip, Config::default()).await
     }
 
+    /// Connects to the server over TLS with the default Config. The domain is 
+    /// checked against the certificate of the server. Enable the tls feature to use it.
+    #[cfg(feature = "tls")]
+    pub async fn connect_tls<A: ToSocketAddrs>(ip: A, domain: &str, tls: Arc<ClientConfig>) -> io::Result<Client<Req, Res>> {
+        Self::connect_tls_with_config(ip, domain, tls, Config::default()).await
+    }
+
+    /// Connects to a WebSocket server like `ws://localhost:50052` with the default Config. 
+    /// Enable the websocket feature to use it.
+    #[cfg(feature = "websocket")]
+    pub async fn connect_ws(url: &str) -> io::Result<Client<Req, Res>> {
+        Self::connect_ws_with_config(url, Config::default()).await
+    }
+
+    /// Connects to a server on a Unix domain socket with the default Config.
+    #[cfg(unix)]
+    pub async fn connect_unix<P: AsRef<Path>>(path: P) -> io::Result<Client<Req, Res>> {
+        Self::connect_unix_with_config(path, Config::default()).await
+    }
+
+    /// Runs the connection over any `Stream` with the default Config, e.g. an 
+    /// in-memory `tokio::io::DuplexStream` or a stream of your own transport.
+    /// Has to be called from within a tokio runtime.
+    pub fn from_stream<S: Stream>(stream: S) -> Client<Req, Res> {
+        Self::from_stream_with_config(stream, Config::default())
+    }
+}
+
+impl<Req: Message, Res: Message, C: Codec<Req> + Codec<Res>> Client<Req, Res, C>{
     /// Connects to the server with a custom Config.
-    pub async fn connect_with_config<A: ToSocketAddrs>(ip: A, config: Config) -> io::Result<Client<Req, Res>> {
-        let stream = TcpStream::connect(ip).await?;
-        let (read, write) = stream.into_split();
-    
+    pub async fn connect_with_config<A: ToSocketAddrs>(ip: A, config: Config<C>) -> io::Result<Client<Req, Res, C>> {
+        let addrs = tokio::net::lookup_host(ip).await?.collect();
+        Self::dial(Dial::Tcp(addrs), config).await
+    }
+
+    /// Connects to the server over TLS with a custom Config. Enable the tls feature to use it.
+    #[cfg(feature = "tls")]
+    pub async fn connect_tls_with_config<A: ToSocketAddrs>(
+        ip: A, 
+        domain: &str, 
+        tls: Arc<ClientConfig>, 
+        config: Config<C>,
+    ) -> io::Result<Client<Req, Res, C>> {
+        let domain = ServerName::try_from(domain.to_string())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+
+        let addrs = tokio::net::lookup_host(ip).await?.collect();
+        Self::dial(Dial::Tls(addrs, domain, TlsConnector::from(tls)), config).await
+    }
+
+    /// Connects to a WebSocket server with a custom Config. Enable the websocket feature to use it.
+    #[cfg(feature = "websocket")]
+    pub async fn connect_ws_with_config(url: &str, config: Config<C>) -> io::Result<Client<Req, Res, C>> {
+        Self::dial(Dial::WebSocket(url.to_string()), config).await
+    }
+
+    /// Connects to a server on a Unix domain socket with a custom Config.
+    #[cfg(unix)]
+    pub async fn connect_unix_with_config<P: AsRef<Path>>(path: P, config: Config<C>) -> io::Result<Client<Req, Res, C>> {
+        Self::dial(Dial::Unix(path.as_ref().to_path_buf()), config).await
+    }
+
+    /// Runs the connection over any `Stream` with a custom Config. 
+    /// `Config::reconnect` is ignored, since there is no way to get another stream.
+    pub fn from_stream_with_config<S: Stream>(stream: S, config: Config<C>) -> Client<Req, Res, C> {
+        let (read, write) = tokio::io::split(stream);
+        Self::from_halves(Box::new(read), Box::new(write), config)
+    }
+
+    /// The first connection has to succeed, even with `Config::reconnect`.
+    async fn dial(dial: Dial, config: Config<C>) -> io::Result<Client<Req, Res, C>> {
+        let (read, write) = dial.connect().await?;
+
+        Ok(match config.reconnect.clone() {
+            Some(reconnect) => Self::reconnecting(dial, read, write, reconnect, config),
+            None => Self::from_halves(read, write, config),
+        })
+    }
+
+    fn reconnecting(
+        dial: Dial, 
+        read: instance::ReadHalf, 
+        write: instance::WriteHalf, 
+        reconnect: Reconnect, 
+        config: Config<C>,
+    ) -> Client<Req, Res, C> {
+        let calls = Arc::new(Calls::new());
+
+        let (emitter_sx, requests) = queue::channel(reconnect.buffer);
+        let (sx, rx) = mpsc::channel(config.emitter_buffer);
+        let emitter = Emitter{sx: emitter_sx, calls: calls.clone(), call_timeout: config.call_timeout};
+
+        Supervisor{ dial, requests, events: sx, calls, config, reconnect, patterns: HashSet::new() }.spawn_on_task(read, write);
+
+        Client{collector: Collector{rx}, emitter, codec: PhantomData}
+    }
+
+    fn from_halves(read: instance::ReadHalf, write: instance::WriteHalf, config: Config<C>) -> Client<Req, Res, C> {
+        let calls = Arc::new(Calls::new());
+
+        let (emitter_sx, rx) = queue::channel(config.collector_buffer);
+        let (link, report) = instance::Link::new(&emitter_sx);
+        instance::Emitter::spawn_on_task(write, rx, config.codec.clone(), config.flush_policy, report);
+
         let (sx, rx) = mpsc::channel(config.emitter_buffer);
-        instance::Collector::spawn_on_task(read, sx, Origin::OnClient, config.timeout);
+        instance::Collector::spawn_on_task(
+            read, sx, link, Origin::OnClient, config.settings(), config.codec.clone(), Some(calls.clone())
+        );
         let collector = Collector{rx};
     
-        let (sx, rx) = mpsc::channel(config.collector_buffer);
-        instance::Emitter::spawn_on_task(write, rx);
-        let emitter = Emitter{sx};
+        let emitter = Emitter{sx: emitter_sx, calls, call_timeout: config.call_timeout};
         
-        Ok(Client{collector, emitter})
+        Client{collector, emitter, codec: PhantomData}
     }
 
     /// Gets the next event if one is available, otherwise it waits until it is.
@@ -59,17 +171,41 @@ impl<Req: Message, Res: Message> Client<Req, Res>{
     }
 
     /// Default method for streaming to the Server.
-    pub async fn emit_request(&self, req: Req) {
+    pub async fn emit_request(&self, req: Req) -> Result<()> {
         self.emitter.emit_request(req).await
     }
 
-    pub fn try_emit(&self, req: Req) {
+    /// Emits without waiting. See `Emitter::try_emit`.
+    pub fn try_emit(&self, req: Req) -> Result<()> {
         self.emitter.try_emit(req)
     }
 
+    /// Sends a request and waits for the response to exactly this request, 
+    /// for at most `Config::call_timeout`. Calls can complete in any order.
+    /// 
+    /// The response doesnt show up as an `Event`.
+    pub async fn call(&self, req: Req) -> Result<Res, RpcError> {
+        self.emitter.call(req).await
+    }
+
+    /// Subscribes to a topic pattern. See `Emitter::subscribe`.
+    pub async fn subscribe(&self, pattern: &str) -> Result<()> {
+        self.emitter.subscribe(pattern).await
+    }
+
+    /// Unsubscribes from a topic pattern. See `Emitter::unsubscribe`.
+    pub async fn unsubscribe(&self, pattern: &str) -> Result<()> {
+        self.emitter.unsubscribe(pattern).await
+    }
+
+    /// Like `call`, but with a custom timeout.
+    pub async fn call_with_timeout(&self, req: Req, timeout: Duration) -> Result<Res, RpcError> {
+        self.emitter.call_with_timeout(req, timeout).await
+    }
+
     /// Splits the Client into Collector and Emitter. The Emitter can be 
     /// cloned for async operations.
-    pub fn into_split(self) -> (Collector<Res>, Emitter<Req>) {
+    pub fn into_split(self) -> (Collector<Res>, Emitter<Req, Res>) {
         (self.collector, self.emitter)
     }
 }
@@ -84,10 +220,7 @@ impl<Res: Message> Collector<Res> {
     /// 
     /// Will return `None` once the connection has ended.
     pub async fn get_event(&mut self) -> Option<Event<Res>> {
-        match self.rx.recv().await {
-            Some((msg, _)) => Some(msg),
-            None => None,
-        }
+        self.rx.recv().await.map(|(msg, _)| msg)
     }
 
     /// Convenience method for applications which only care about responses.
@@ -122,39 +255,162 @@ impl<Res: Message> Collector<Res> {
 }
 
 #[derive(Debug, Clone)]
-pub struct Emitter<Req: Message>{
-    sx: mpsc::Sender<Req>
+pub struct Emitter<Req: Message, Res: Message>{
+    sx: queue::Sender<Outgoing<Req>>,
+    calls: Arc<Calls<Res>>,
+    call_timeout: Duration,
 }
 
-impl<Req: Message> Emitter<Req> {
+impl<Req: Message, Res: Message> Emitter<Req, Res> {
     /// Default method for streaming to the Server.
-    pub async fn emit_request(&self, req: Req) {
-        match self.sx.send(req).await {
-            Ok(_) => (),
-            Err(_) => unreachable!(),
+    /// 
+    /// Fails with `Error::Closed` once the connection has ended.
+    pub async fn emit_request(&self, req: Req) -> Result<()> {
+        self.sx.send(Outgoing::Msg(req)).await.map_err(|_| Error::Closed)
+    }
+
+    /// Emits without waiting. Fails with `Error::Full` if the buffer of the 
+    /// Emitter is full, or with `Error::Closed` once the connection has ended.
+    pub fn try_emit(&self, req: Req) -> Result<()> {
+        match self.sx.try_send(Outgoing::Msg(req)){
+            Ok(_) => Ok(()),
+            Err(queue::TrySendError::Full(_)) => Err(Error::Full),
+            Err(queue::TrySendError::Closed(_)) => Err(Error::Closed),
+        }
+    }
+
+    /// Subscribes to a topic pattern, so Responses the Server publishes to a 
+    /// matching topic arrive as normal Responses. Topics are split into segments 
+    /// by `.`, and a `*` segment matches any one segment, e.g. `match.*.score`.
+    /// With `Config::reconnect`, every new connection subscribes again.
+    /// 
+    /// Fails with `Error::InvalidPattern` if a segment is empty or has a `*` 
+    /// next to something else, and with `Error::Closed` once the connection has ended.
+    pub async fn subscribe(&self, pattern: &str) -> Result<()> {
+        if !instance::valid_pattern(pattern) {
+            return Err(Error::InvalidPattern)
+        }
+        self.sx.send(Outgoing::Subscribe(pattern.to_string())).await.map_err(|_| Error::Closed)
+    }
+
+    /// Unsubscribes from a topic pattern which was subscribed to before. 
+    /// Fails like `subscribe`.
+    pub async fn unsubscribe(&self, pattern: &str) -> Result<()> {
+        if !instance::valid_pattern(pattern) {
+            return Err(Error::InvalidPattern)
+        }
+        self.sx.send(Outgoing::Unsubscribe(pattern.to_string())).await.map_err(|_| Error::Closed)
+    }
+
+    /// Sends a request and waits for the response to exactly this request, 
+    /// for at most `Config::call_timeout`. Calls can complete in any order.
+    /// 
+    /// The response doesnt show up as an `Event`.
+    pub async fn call(&self, req: Req) -> Result<Res, RpcError> {
+        self.call_with_timeout(req, self.call_timeout).await
+    }
+
+    /// Like `call`, but with a custom timeout.
+    pub async fn call_with_timeout(&self, req: Req, timeout: Duration) -> Result<Res, RpcError> {
+        let (call, rx) = self.calls.register().ok_or(RpcError::Disconnected)?;
+
+        if self.sx.send(Outgoing::Call(call, req)).await.is_err() {
+            self.calls.cancel(call);
+            return Err(RpcError::Disconnected)
+        }
+
+        match tokio::time::timeout(timeout, rx).await {
+            Ok(Ok(Ok(res))) => Ok(res),
+            Ok(Ok(Err(CallError::IllegalData(illegal)))) => Err(RpcError::IllegalData(illegal)),
+            Ok(Ok(Err(CallError::Failed(reason)))) => Err(RpcError::Failed(reason)),
+            Ok(Err(_)) => Err(RpcError::Disconnected),
+            Err(_) => {
+                self.calls.cancel(call);
+                Err(RpcError::Timeout)
+            },
         }
     }
+}
+
+/// A call made with `Client::call` failed.
+#[derive(Debug, Clone)]
+pub enum RpcError{
+    /// No response arrived within the timeout.
+    Timeout,
+    /// The connection ended before a response arrived.
+    Disconnected,
+    /// The response couldnt be decoded.
+    IllegalData(Illegal),
+    /// The request or the response couldnt be encoded, with the reason.
+    Failed(String),
+}
 
-    pub fn try_emit(&self, req: Req) {
-        match self.sx.try_send(req){
-            Ok(_) => (),
-            Err(e) => panic!("{}", e),
+impl fmt::Display for RpcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RpcError::Timeout => write!(f, "the call timed out"),
+            RpcError::Disconnected => write!(f, "the connection ended before the call completed"),
+            RpcError::IllegalData(illegal) => write!(f, "the response couldnt be decoded: {}", illegal.err),
+            RpcError::Failed(reason) => write!(f, "the call couldnt be encoded: {}", reason),
         }
     }
 }
 
+impl error::Error for RpcError {}
+
 /// Config for the Client
-pub struct Config{
+#[derive(Debug, Clone)]
+pub struct Config<C = Bincode>{
     /// If no new Responses appear within this duration, we drop the collector.
     pub timeout: Duration,
     /// The size of the channel buffer for the Emitter.
     pub emitter_buffer: usize,
     /// the size of the channel buffer for the Collector.
     pub collector_buffer: usize,
+    /// Frames larger than this many bytes are skipped and reported as `Event::Oversized`.
+    pub max_frame_size: usize,
+    /// The `Codec` used to encode Requests and decode Responses.
+    pub codec: C,
+    /// How long `call` waits for a response.
+    pub call_timeout: Duration,
+    /// How often the Server is pinged. `None` disables sending heartbeats, 
+    /// pings of the Server are always answered.
+    pub heartbeat_interval: Option<Duration>,
+    /// If the Server doesnt answer a ping within this duration, the connection 
+    /// ends with `DisconnectEvent::Unresponsive`.
+    pub heartbeat_timeout: Duration,
+    /// When Requests are flushed onto the stream.
+    pub flush_policy: FlushPolicy,
+    /// Connects again after the connection ended, unless the Client was kicked. 
+    /// Requests which were still queued go out on the next connection, the ones 
+    /// being written when it broke are lost. `None` lets the Client end with the connection.
+    pub reconnect: Option<Reconnect>,
 }
 
-impl Default for Config{
-    fn default() -> Config {
-        Config { timeout: Duration::MAX, emitter_buffer: 3, collector_buffer: 3 }
+impl<C> Config<C> {
+    fn settings(&self) -> instance::Settings {
+        instance::Settings{
+            timeout: self.timeout,
+            max_frame_size: self.max_frame_size,
+            heartbeat_interval: self.heartbeat_interval,
+            heartbeat_timeout: self.heartbeat_timeout,
+        }
+    }
+}
+
+impl<C: Default> Default for Config<C>{
+    fn default() -> Config<C> {
+        Config { 
+            timeout: Duration::MAX, 
+            emitter_buffer: 3, 
+            collector_buffer: 3, 
+            max_frame_size: 16 * 1024 * 1024,
+            codec: C::default(),
+            call_timeout: Duration::from_secs(30),
+            heartbeat_interval: Some(Duration::from_secs(15)),
+            heartbeat_timeout: Duration::from_secs(30),
+            flush_policy: FlushPolicy::Immediate,
+            reconnect: None,
+        }
     }
 }
\ No newline at end of file
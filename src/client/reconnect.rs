@@ -0,0 +1,205 @@
+use std::{collections::{HashSet, VecDeque, hash_map::RandomState}, hash::{BuildHasher, Hasher}, sync::Arc, time::Duration};
+
+use tokio::sync::mpsc;
+
+use crate::{Message, instance::{self, Calls, Outgoing, queue}, event::{Origin, Event, DisconnectEvent}, codec::Codec};
+
+use super::{Config, dial::Dial};
+
+/// How a Client connects again after its connection ended. See `Config::reconnect`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reconnect{
+    /// The delay before the first attempt.
+    pub initial_delay: Duration,
+    /// The delay doesnt grow beyond this.
+    pub max_delay: Duration,
+    /// Every failed attempt multiplies the delay by this.
+    pub multiplier: f64,
+    /// Up to this fraction of every delay is randomly taken off, so many Clients 
+    /// dont reconnect all at once. Between 0 and 1.
+    pub jitter: f64,
+    /// Gives up after this many failed attempts in a row. `None` tries forever.
+    pub max_attempts: Option<u32>,
+    /// How many Requests are kept while the connection is down. Once full, 
+    /// `emit_request` waits and `try_emit` fails with `Error::Full`.
+    pub buffer: usize,
+}
+
+impl Default for Reconnect {
+    fn default() -> Self {
+        Reconnect{
+            initial_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(30),
+            multiplier: 2.0,
+            jitter: 0.2,
+            max_attempts: None,
+            buffer: 64,
+        }
+    }
+}
+
+impl Reconnect {
+    /// The delay after `delay`.
+    fn next(&self, delay: Duration) -> Duration {
+        Duration::try_from_secs_f64(delay.as_secs_f64() * self.multiplier)
+            .unwrap_or(self.max_delay)
+            .min(self.max_delay)
+    }
+
+    fn jitter(&self, delay: Duration) -> Duration {
+        // every RandomState gets new random keys, which is plenty for spreading out 
+        // reconnects and spares us a dependency on rand. Not fit for anything secret.
+        let random = RandomState::new().build_hasher().finish() as f64 / u64::MAX as f64;
+        delay.mul_f64(1.0 - self.jitter.clamp(0.0, 1.0) * random)
+    }
+}
+
+/// How a connection of a reconnecting Client ended.
+enum Ended{
+    /// Every Emitter of the Client is gone.
+    Client,
+    Connection(Option<DisconnectEvent>),
+}
+
+/// Lives on a seperate task
+/// 
+/// Runs one connection after another, handing each the Requests queued by the Emitters 
+/// of the Client and the events of each to its Collector.
+pub(crate) struct Supervisor<Req: Message, Res: Message, C>{
+    pub dial: Dial,
+    pub requests: queue::Receiver<Outgoing<Req>>,
+    pub events: mpsc::Sender<(Event<Res>, Origin)>,
+    pub calls: Arc<Calls<Res>>,
+    pub config: Config<C>,
+    pub reconnect: Reconnect,
+    /// The topic patterns we are subscribed to, every new connection subscribes to them again.
+    pub patterns: HashSet<String>,
+}
+
+impl<Req: Message, Res: Message, C: Codec<Req> + Codec<Res>> Supervisor<Req, Res, C> {
+    pub fn spawn_on_task(self, read: instance::ReadHalf, write: instance::WriteHalf) {
+        tokio::spawn(self.run(read, write));
+    }
+
+    async fn run(mut self, mut read: instance::ReadHalf, mut write: instance::WriteHalf) {
+        // Requests which didnt make it onto the last connection
+        let mut unsent = VecDeque::new();
+        loop{
+            match self.connection(read, write, &mut unsent).await {
+                Ended::Client => return,
+                // a kicked Client shouldnt come right back
+                Ended::Connection(Some(DisconnectEvent::KickedByServer{ .. })) => return,
+                Ended::Connection(_) => (),
+            }
+
+            (read, write) = match self.connect_again().await {
+                Some(halves) => halves,
+                // dropping the events sender ends the Collector of the Client
+                None => return,
+            };
+            self.calls.reopen();
+            // the Server forgot them along with the last connection
+            for pattern in &self.patterns {
+                unsent.push_front(Outgoing::Subscribe(pattern.clone()));
+            }
+            self.events.send((Event::Reconnected, Origin::OnClient)).await.ok();
+        }
+    }
+
+    /// Runs one connection until it ends.
+    async fn connection(
+        &mut self, 
+        read: instance::ReadHalf, 
+        write: instance::WriteHalf, 
+        unsent: &mut VecDeque<Outgoing<Req>>,
+    ) -> Ended {
+        let (conn, rx) = queue::channel(self.config.collector_buffer);
+        let (link, report) = instance::Link::new(&conn);
+        instance::Emitter::spawn_on_task(write, rx, self.config.codec.clone(), self.config.flush_policy, report);
+
+        let (sx, rx) = mpsc::channel(self.config.emitter_buffer);
+        instance::Collector::spawn_on_task(
+            read, sx, link, Origin::OnClient, self.config.settings(), self.config.codec.clone(), Some(self.calls.clone())
+        );
+        let mut events = tokio::spawn(forward_events(rx, self.events.clone()));
+
+        loop{
+            let out = match unsent.pop_front() {
+                Some(out) => out,
+                None => tokio::select! {
+                    reason = &mut events => {
+                        requeue(unsent, &conn, None);
+                        return Ended::Connection(reason.ok().flatten())
+                    },
+                    out = self.requests.recv() => match out {
+                        Some(out) => { self.track(&out); out },
+                        None => return Ended::Client,
+                    },
+                },
+            };
+
+            if let Err(out) = conn.send(out).await {
+                // the connection broke, the Requests go out on the next one
+                requeue(unsent, &conn, Some(out));
+                return Ended::Connection(events.await.ok().flatten())
+            }
+        }
+    }
+
+    /// Remembers what `out` changes about our subscriptions.
+    fn track(&mut self, out: &Outgoing<Req>) {
+        match out {
+            Outgoing::Subscribe(pattern) => { self.patterns.insert(pattern.clone()); },
+            Outgoing::Unsubscribe(pattern) => { self.patterns.remove(pattern); },
+            _ => (),
+        }
+    }
+
+    /// Tries to connect with a growing delay. `None` once it gives up, or once the Client is gone.
+    async fn connect_again(&mut self) -> Option<(instance::ReadHalf, instance::WriteHalf)> {
+        let mut delay = self.reconnect.initial_delay;
+        let mut attempt = 0;
+        loop{
+            attempt += 1;
+            if self.requests.is_closed() || self.reconnect.max_attempts.is_some_and(|max| attempt > max) {
+                return None
+            }
+
+            let wait = self.reconnect.jitter(delay);
+            self.events.send((Event::Reconnecting{ attempt, delay: wait }, Origin::OnClient)).await.ok();
+            tokio::time::sleep(wait).await;
+
+            if let Ok(halves) = self.dial.connect().await {
+                return Some(halves)
+            }
+            delay = self.reconnect.next(delay);
+        }
+    }
+}
+
+/// Puts the Requests still queued on a broken connection, and then `failed`, in front of 
+/// the `unsent` ones. Whatever the Emitter was writing when it broke is lost.
+fn requeue<Req>(unsent: &mut VecDeque<Outgoing<Req>>, conn: &queue::Sender<Outgoing<Req>>, failed: Option<Outgoing<Req>>) {
+    let mut requeued = conn.take_unsent();
+    requeued.extend(failed);
+    // their callers already got an `RpcError`, and the subscriptions are renewed anyway
+    requeued.retain(|out| !matches!(out, Outgoing::Call(..) | Outgoing::Subscribe(_) | Outgoing::Unsubscribe(_)));
+    requeued.append(unsent);
+    *unsent = requeued;
+}
+
+/// Hands the events of one connection to the Collector of the Client. Returns why the connection ended.
+async fn forward_events<Res: Message>(
+    mut rx: mpsc::Receiver<(Event<Res>, Origin)>, 
+    events: mpsc::Sender<(Event<Res>, Origin)>,
+) -> Option<DisconnectEvent> {
+    let mut reason = None;
+    while let Some((event, origin)) = rx.recv().await {
+        if let Event::Disconnect(disconnect) = &event {
+            reason = Some(disconnect.clone());
+        }
+        // the Collector of the Client might be gone, the connection still serves calls
+        events.send((event, origin)).await.ok();
+    }
+    reason
+}
@@ -0,0 +1,126 @@
+//! Serialization formats for the payload of a frame.
+//!
+//! Every `Server` and `Client` is parameterised over a `Codec`, which is picked
+//! through its `Config`. `Bincode` is the default, the other codecs are enabled
+//! with the json, postcard and msgpack features. Both sides of a connection have
+//! to use the same codec.
+
+use std::error;
+
+use bincode::{Encode, Decode, config::{self, Configuration}, error::DecodeError};
+#[cfg(any(feature = "json", feature = "postcard", feature = "msgpack"))]
+use serde::{Serialize, de::DeserializeOwned};
+
+/// Re-export of the bincode version used by the `Bincode` codec.
+pub use bincode;
+
+/// Any error a `Codec` can produce.
+pub type Error = Box<dyn error::Error + Send + Sync>;
+
+/// Turns `Msg`s into bytes and back. Decoding has to consume the entire payload.
+pub trait Codec<Msg>: Clone + Send + Sync + 'static {
+    /// Appends the encoded `msg` to `buf`.
+    fn encode(&self, msg: &Msg, buf: &mut Vec<u8>) -> Result<(), Error>;
+
+    /// Decodes a `msg` from the payload of a frame.
+    fn decode(&self, payload: &[u8]) -> Result<Msg, Error>;
+}
+
+/// The compact bincode format. `#[derive(Encode, Decode)]` makes your types work with it.
+/// 
+/// Uses `bincode::config::standard()` by default. Any other bincode configuration 
+/// can be used, e.g. for interop with existing bincode based peers:
+/// ```
+/// use kumoko::codec::{Bincode, bincode::config};
+/// 
+/// // fixed size big-endian integers, and at most 1 MiB per decoded message
+/// let codec = Bincode::with_config(
+///     config::standard().with_fixed_int_encoding().with_big_endian().with_limit::<1048576>()
+/// );
+/// ```
+/// The limit protects against messages claiming huge collections.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Bincode<Cfg = Configuration>{
+    config: Cfg,
+}
+
+impl<Cfg: config::Config> Bincode<Cfg> {
+    /// Uses a custom bincode configuration.
+    pub const fn with_config(config: Cfg) -> Self {
+        Bincode{ config }
+    }
+}
+
+impl<Msg, Cfg> Codec<Msg> for Bincode<Cfg> 
+    where Msg: Encode + Decode<()>, Cfg: config::Config + Send + Sync + 'static,
+{
+    fn encode(&self, msg: &Msg, buf: &mut Vec<u8>) -> Result<(), Error> {
+        bincode::encode_into_std_write(msg, buf, self.config)?;
+        Ok(())
+    }
+
+    fn decode(&self, payload: &[u8]) -> Result<Msg, Error> {
+        let (msg, read) = bincode::decode_from_slice(payload, self.config)?;
+        match read == payload.len() {
+            true => Ok(msg),
+            false => Err(DecodeError::Other("frame contains trailing bytes").into()),
+        }
+    }
+}
+
+/// Human readable JSON. Works with any serde type. Enable the json feature to use it.
+#[cfg(feature = "json")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Json;
+
+#[cfg(feature = "json")]
+impl<Msg: Serialize + DeserializeOwned> Codec<Msg> for Json {
+    fn encode(&self, msg: &Msg, buf: &mut Vec<u8>) -> Result<(), Error> {
+        Ok(serde_json::to_writer(buf, msg)?)
+    }
+
+    fn decode(&self, payload: &[u8]) -> Result<Msg, Error> {
+        Ok(serde_json::from_slice(payload)?)
+    }
+}
+
+/// The compact postcard format. Works with any serde type. Enable the postcard feature to use it.
+#[cfg(feature = "postcard")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Postcard;
+
+#[cfg(feature = "postcard")]
+impl<Msg: Serialize + DeserializeOwned> Codec<Msg> for Postcard {
+    fn encode(&self, msg: &Msg, buf: &mut Vec<u8>) -> Result<(), Error> {
+        buf.extend(postcard::to_allocvec(msg)?);
+        Ok(())
+    }
+
+    fn decode(&self, payload: &[u8]) -> Result<Msg, Error> {
+        match postcard::take_from_bytes(payload)? {
+            (msg, []) => Ok(msg),
+            _ => Err("frame contains trailing bytes".into()),
+        }
+    }
+}
+
+/// MessagePack, with structs encoded as maps. Works with any serde type.
+/// Enable the msgpack feature to use it.
+#[cfg(feature = "msgpack")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MsgPack;
+
+#[cfg(feature = "msgpack")]
+impl<Msg: Serialize + DeserializeOwned> Codec<Msg> for MsgPack {
+    fn encode(&self, msg: &Msg, buf: &mut Vec<u8>) -> Result<(), Error> {
+        Ok(rmp_serde::encode::write_named(buf, msg)?)
+    }
+
+    fn decode(&self, mut payload: &[u8]) -> Result<Msg, Error> {
+        let msg = rmp_serde::decode::from_read(&mut payload)?;
+        match payload.is_empty() {
+            true => Ok(msg),
+            false => Err("frame contains trailing bytes".into()),
+        }
+    }
+}
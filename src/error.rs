@@ -0,0 +1,28 @@
+use std::{fmt, error};
+
+/// The error type of kumoko.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error{
+    /// The connection has ended, or the Server was shut down.
+    Closed,
+    /// The buffer of the Emitter is full. Only returned by `try_emit`.
+    Full,
+    /// A topic pattern has an empty segment, or a `*` which isnt a whole segment. 
+    /// Only returned by `subscribe` and `unsubscribe`.
+    InvalidPattern,
+}
+
+/// A Result with a kumoko `Error`.
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Closed => write!(f, "the connection is closed"),
+            Error::Full => write!(f, "the buffer of the emitter is full"),
+            Error::InvalidPattern => write!(f, "the topic pattern is invalid"),
+        }
+    }
+}
+
+impl error::Error for Error {}
@@ -1,15 +1,40 @@
 //! Definitions for Connection Events
 
-use std::{sync::Arc, io};
-use bincode::error::DecodeError;
-use crate::Message;
+use std::{sync::Arc, io, error, fmt, net::SocketAddr, time::{Duration, SystemTime}};
+use crate::{Message, codec};
+
+/// Identifies a Client of a Server. Ids are never reused, so an id of a Client 
+/// which disconnected can never address a newer connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ClientId(u64);
+
+impl ClientId {
+    #[cfg_attr(not(feature = "server"), allow(dead_code))]
+    pub(crate) fn new(id: u64) -> Self {
+        ClientId(id)
+    }
+
+    /// The raw id, e.g. for logging.
+    pub fn get(self) -> u64 {
+        self.0
+    }
+}
+
+impl fmt::Display for ClientId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
 
 /// Describes which client an `Event` originated from. `.into()`
 /// can be used to transform into a `Target` to reply to.
 #[derive(Debug, Clone, Copy)]
 pub enum Origin{
     /// The Id of the Client.
-    Id(usize),
+    Id(ClientId),
+    /// The Id of the Client and the call id of a request sent with `Client::call`. 
+    /// Replying to this Origin answers exactly that call.
+    Call(ClientId, u64),
     /// A Client can ignore this entirely.
     OnClient,
 }
@@ -18,45 +43,127 @@ pub enum Origin{
 #[derive(Debug, Clone)]
 pub enum Event<Msg: Message>{
     /// It connected!
-    Connect,
+    Connect(ConnectionInfo),
     /// It sent a Message!
     Message(Msg),
     /// It sent Illegal data!
     IllegalData(Illegal),
+    /// It sent a frame larger than the configured maximum! The frame was skipped.
+    Oversized(Oversized),
+    /// It answered a heartbeat! Includes the measured round-trip time.
+    Rtt(Duration),
+    /// It subscribed to a topic pattern! Only seen by the Server. See `Emitter::publish`.
+    Subscribe(String),
+    /// It unsubscribed from a topic pattern! Only seen by the Server.
+    Unsubscribe(String),
+    /// It couldnt keep up with its Responses! Only seen by the Server. See `BackpressurePolicy`.
+    Backpressure(Backpressure),
     /// It disconnected!
     Disconnect(DisconnectEvent),
+    /// It is about to try to connect again after `delay`! Only seen by Clients 
+    /// with `Config::reconnect`, `attempt` counts from 1.
+    Reconnecting{ attempt: u32, delay: Duration },
+    /// It connected again! Its subscriptions are renewed, then the Requests emitted meanwhile are sent.
+    Reconnected,
+    /// A Message to it couldnt be encoded and was dropped! A failed call 
+    /// fails with `RpcError::Failed` instead.
+    EncodeError(Arc<dyn error::Error + Send + Sync>),
     /// An Error which didnt break the connection occured.
     RealError(Arc<io::Error>),
 }
 
-/// The connection was broken by:
+/// What the Server did about a Client with a full buffer. Only sent when this changes,
+/// and dropped itself if the event buffer is full.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Backpressure{
+    /// The Server waits for the Client, which holds up every other Client too.
+    Blocking,
+    /// Responses to the Client are dropped.
+    Dropping,
+    /// The Client caught up again. Includes how many Responses were dropped meanwhile.
+    Recovered{ dropped: usize },
+}
+
+/// Information about a new connection.
 #[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ConnectionInfo{
+    /// The address of the peer. Only available on TCP based transports.
+    pub peer_addr: Option<SocketAddr>,
+    /// The address the peer connected to. Only available on TCP based transports.
+    pub local_addr: Option<SocketAddr>,
+    /// When the connection was accepted.
+    pub connected_at: SystemTime,
+    /// What the connection runs on.
+    pub transport: TransportKind,
+    /// The credentials of the peer process. Only available on Unix domain sockets.
+    pub credentials: Option<Credentials>,
+}
+
+impl ConnectionInfo {
+    /// A connection accepted just now, without any addresses or credentials. 
+    /// Custom `Listener`s can fill in the rest.
+    pub fn new(transport: TransportKind) -> Self {
+        ConnectionInfo{ peer_addr: None, local_addr: None, connected_at: SystemTime::now(), transport, credentials: None }
+    }
+}
+
+/// The kind of transport a connection runs on.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum TransportKind{
+    Tcp,
+    /// TLS over TCP.
+    Tls,
+    /// WebSocket over TCP.
+    WebSocket,
+    Unix,
+    /// A stream of a custom `Listener`.
+    Custom,
+}
+
+/// Credentials of the process on the other end of a Unix domain socket.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Credentials{
+    pub uid: u32,
+    pub gid: u32,
+    /// Not every platform reports the pid.
+    pub pid: Option<i32>,
+}
+
+/// The connection was broken by:
+#[derive(Debug, Clone)]
 pub enum DisconnectEvent{
-    /// a call to close it.
+    /// the peer closing the stream without giving a reason.
     Clean,
-    /// an error.
-    Dirty,
+    /// the peer closing the connection with a close frame.
+    PeerClosed{ code: u16, reason: String },
+    /// the Server kicking the Client.
+    KickedByServer{ reason: String },
+    /// the Server shutting down. Only seen by Clients.
+    ServerShutdown,
+    /// the peer not sending anything within the configured timeout.
+    Timeout,
+    /// the peer not answering a heartbeat within the heartbeat timeout.
+    Unresponsive,
+    /// the Client not keeping up with its Responses. See `BackpressurePolicy::Disconnect`.
+    SlowConsumer,
+    /// the peer breaking the protocol, e.g. with a malformed control frame.
+    ProtocolViolation(&'static str),
+    /// an error of the underlying stream.
+    IoError(Arc<io::Error>),
 }
 
-/// The sent Message couldnt be decoded. Includes the raw bytes and the `DecodeError`.
+/// The sent Message couldnt be decoded. Includes the raw bytes and the error of the `Codec`.
 #[derive(Debug, Clone)]
 pub struct Illegal{
-    pub err: Arc<DecodeError>,
+    pub err: Arc<dyn error::Error + Send + Sync>,
     pub vec: Vec<u8>,
 }
 
-impl<Msg: Message> Event<Msg> {
-    pub(crate) fn clean() -> Self{
-        Self::Disconnect(DisconnectEvent::Clean)
-    }
-
-    pub(crate) fn dirty() -> Self{
-        Self::Disconnect(DisconnectEvent::Dirty)
-    }
-
-    pub(crate) fn from_err(err: io::Error) -> Self{
-        Self::RealError(Arc::new(err))
-    }
+/// A frame exceeded the maximum frame size. Includes the announced length and the maximum.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Oversized{
+    pub len: usize,
+    pub max: usize,
 }
 
 impl<Msg: Message> From<Msg> for Event<Msg> {
@@ -65,20 +172,31 @@ impl<Msg: Message> From<Msg> for Event<Msg> {
     }
 }
 
-impl<Msg: Message> From<Illegal> for Event<Msg> {
-    fn from(i: Illegal) -> Self {
-        Self::IllegalData(i)
+impl From<(Vec<u8>, codec::Error)> for Illegal {
+    fn from((vec, err): (Vec<u8>, codec::Error)) -> Self {
+        Self{ vec, err: err.into() }
     }
 }
 
-impl From<(Vec<u8>, bincode::error::DecodeError)> for Illegal {
-    fn from((vec, err): (Vec<u8>, bincode::error::DecodeError)) -> Self {
-        Self{ vec, err: Arc::new(err) }
+impl Origin {
+    /// The Client this came from. `None` on a Client.
+    pub fn client_id(&self) -> Option<ClientId> {
+        match self {
+            Origin::Id(id) | Origin::Call(id, _) => Some(*id),
+            Origin::OnClient => None,
+        }
     }
 }
 
-impl<U: Into<usize>> From<U> for Origin{
-    fn from(id: U) -> Self {
-        Self::Id(id.into())
+impl From<ClientId> for Origin{
+    fn from(id: ClientId) -> Self {
+        Self::Id(id)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(unix)]
+impl From<tokio::net::unix::UCred> for Credentials {
+    fn from(cred: tokio::net::unix::UCred) -> Self {
+        Self{ uid: cred.uid(), gid: cred.gid(), pid: cred.pid() }
+    }
+}
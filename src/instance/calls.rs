@@ -0,0 +1,73 @@
+use std::{collections::HashMap, fmt, sync::{Mutex, MutexGuard, atomic::{AtomicU64, Ordering}}};
+
+use tokio::sync::oneshot;
+
+use crate::event::Illegal;
+
+type Pending<Msg> = HashMap<u64, oneshot::Sender<Result<Msg, CallError>>>;
+
+/// Why a call got no response.
+#[derive(Debug)]
+#[cfg_attr(not(feature = "client"), allow(dead_code))]
+pub(crate) enum CallError{
+    /// The response couldnt be decoded.
+    IllegalData(Illegal),
+    /// The request or the response couldnt be encoded.
+    Failed(String),
+}
+
+/// The pending calls of a Client, shared between its Emitter and Collector.
+pub(crate) struct Calls<Msg>{
+    next: AtomicU64,
+    /// `None` once the connection has ended.
+    pending: Mutex<Option<Pending<Msg>>>,
+}
+
+#[cfg_attr(not(feature = "client"), allow(dead_code))]
+impl<Msg> Calls<Msg> {
+    pub fn new() -> Self {
+        Calls{ next: AtomicU64::new(0), pending: Mutex::new(Some(HashMap::new())) }
+    }
+
+    /// Reserves a new call id. Returns `None` if the connection has ended.
+    pub fn register(&self) -> Option<(u64, oneshot::Receiver<Result<Msg, CallError>>)> {
+        let call = self.next.fetch_add(1, Ordering::Relaxed);
+        let (sx, rx) = oneshot::channel();
+        self.lock().as_mut()?.insert(call, sx);
+        Some((call, rx))
+    }
+
+    /// Completes a call. Replies to unknown calls, e.g. ones which timed out, are dropped.
+    pub fn complete(&self, call: u64, res: Result<Msg, CallError>) {
+        let sx = self.lock().as_mut().and_then(|pending| pending.remove(&call));
+        if let Some(sx) = sx {
+            sx.send(res).ok();
+        }
+    }
+
+    pub fn cancel(&self, call: u64) {
+        if let Some(pending) = self.lock().as_mut() {
+            pending.remove(&call);
+        }
+    }
+
+    /// Fails every pending and future call.
+    pub fn close(&self) {
+        self.lock().take();
+    }
+
+    /// Accepts calls again, for a new connection.
+    pub fn reopen(&self) {
+        self.lock().get_or_insert_with(HashMap::new);
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Option<Pending<Msg>>> {
+        self.pending.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl<Msg> fmt::Debug for Calls<Msg> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Calls").field("next", &self.next).finish_non_exhaustive()
+    }
+}
@@ -1,56 +1,193 @@
-use std::{io::{self, ErrorKind}, time::Duration};
+use std::{io::{self, ErrorKind}, time::Duration, sync::Arc};
 
-use bincode::{config::Configuration, error::DecodeError};
-use tokio::{net::tcp::OwnedReadHalf, sync::mpsc};
-use crate::{Message, event::{Origin, Event, Illegal}};
+use bytes::{Buf, BytesMut};
+use tokio::{io::AsyncReadExt, sync::{mpsc, oneshot}, task::JoinHandle, time::Instant};
+use crate::{Message, codec::{self, Codec}, event::{Origin, Event, DisconnectEvent, Oversized, Illegal}};
 
-use super::ring_buffer::RingBuffer;
+use super::{ReadHalf, Calls, CallError, Outgoing, queue, frame::{self, Header, Kind, HEADER_LEN}};
 
-pub struct Collector<Msg: Message>{
-    stream: OwnedReadHalf,
+/// How many bytes we try to read at once.
+const READ_CHUNK: usize = 4096;
+
+/// How a Collector watches over its connection. Built from the Config.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Settings{
+    pub timeout: Duration,
+    pub max_frame_size: usize,
+    pub heartbeat_interval: Option<Duration>,
+    pub heartbeat_timeout: Duration,
+}
+
+/// Ties a Collector to the Emitter of the same connection.
+pub(crate) struct Link<Out>{
+    /// For heartbeats. Weak, so the Emitter still closes once everyone else is done with it.
+    pub emitter: queue::WeakSender<Outgoing<Out>>,
+    /// Resolves once the Emitter closed the connection with a close frame.
+    pub closed: oneshot::Receiver<DisconnectEvent>,
+    /// Messages the Emitter couldnt encode.
+    pub unsendable: mpsc::UnboundedReceiver<Unsendable>,
+}
+
+impl<Out> Link<Out> {
+    /// Links a Collector to the Emitter behind `emitter`. The Emitter gets the `Report`.
+    pub fn new(emitter: &queue::Sender<Outgoing<Out>>) -> (Self, Report) {
+        let (closed_sx, closed) = oneshot::channel();
+        let (unsendable_sx, unsendable) = mpsc::unbounded_channel();
+        let link = Link{ emitter: emitter.downgrade(), closed, unsendable };
+        (link, Report{ closed: closed_sx, unsendable: unsendable_sx })
+    }
+}
+
+/// The Emitters end of a `Link`.
+pub(crate) struct Report{
+    /// Tells the Collector that we closed the connection, or that it broke.
+    pub closed: oneshot::Sender<DisconnectEvent>,
+    pub unsendable: mpsc::UnboundedSender<Unsendable>,
+}
+
+/// A Message which couldnt be encoded, and the call it belonged to, if any.
+pub(crate) struct Unsendable{
+    pub call: Option<u64>,
+    pub err: codec::Error,
+}
+
+pub struct Collector<Msg: Message, Out, C>{
+    stream: ReadHalf,
     sx: mpsc::Sender<(Event<Msg>, Origin)>,
+    emitter: queue::WeakSender<Outgoing<Out>>,
     id: Origin,
-    timeout: Duration,
-    buffer: RingBuffer,
-    config: Configuration,
+    settings: Settings,
+    buffer: BytesMut,
+    codec: C,
+    /// Bytes of an oversized frame which still have to be thrown away.
+    discard: usize,
+    /// Pending calls. Only Clients make calls.
+    calls: Option<Arc<Calls<Msg>>>,
+    /// The next ping is sent at this point.
+    next_ping: Instant,
+    /// The id and send time of the ping we are waiting on.
+    ping: Option<(u64, Instant)>,
+    ping_count: u64,
+    /// When the last data frame arrived. Heartbeats dont count, they would keep 
+    /// an idle connection alive forever.
+    last_activity: Instant,
 }
 
-impl<Msg: Message> Collector<Msg>{
+impl<Msg: Message, Out: Message, C: Codec<Msg>> Collector<Msg, Out, C>{
     pub fn spawn_on_task(
-        stream: OwnedReadHalf, 
+        stream: ReadHalf, 
         sx: mpsc::Sender<(Event<Msg>, Origin)>, 
+        link: Link<Out>,
         id: Origin,
-        timeout: Duration,
-    ) {
-        let config = bincode::config::standard();
-        Collector{stream, sx, id, timeout, buffer:RingBuffer::new(), config}.collect_loop();
+        settings: Settings,
+        codec: C,
+        calls: Option<Arc<Calls<Msg>>>,
+    ) -> JoinHandle<()> {
+        let Link{ emitter, closed, unsendable } = link;
+        let buffer = BytesMut::with_capacity(READ_CHUNK);
+        let next_ping = Instant::now() + settings.heartbeat_interval.unwrap_or_default();
+        Collector{
+            stream, sx, emitter, id, settings, buffer, codec, discard: 0, calls, next_ping, ping: None, ping_count: 0,
+            last_activity: Instant::now(),
+        }.spawn(closed, unsendable)
     }
 
-    fn collect_loop(mut self) {
+    fn spawn(
+        mut self, 
+        closed: oneshot::Receiver<DisconnectEvent>, 
+        unsendable: mpsc::UnboundedReceiver<Unsendable>,
+    ) -> JoinHandle<()> {
         tokio::spawn(async move{
-            loop{
-                tokio::task::yield_now().await;
-                let sx_clone = self.sx.clone();
-                
-                tokio::select! {
-                    biased;
-                    _ = sx_clone.closed() => { return }
-                    _ = tokio::time::sleep(self.timeout) => { return }
-
-                    data = self.collect_data() => {
-                        match data {
-                            Ok(Status::Finish) => return self.send_event(Event::clean()).await,
-                            Ok(Status::Continue) => (),
-                            Err(err) => match err.kind() {
-                                ErrorKind::WouldBlock => (),
-                                ErrorKind::ConnectionReset => return self.send_event(Event::dirty()).await,
-                                _ => self.send_event(Event::from_err(err)).await,
-                            },
-                        }
-                    }
-                };
+            self.collect_loop(closed, unsendable).await;
+            if let Some(calls) = &self.calls {
+                calls.close();
             }
-        });
+        })
+    }
+
+    async fn collect_loop(
+        &mut self, 
+        mut closed: oneshot::Receiver<DisconnectEvent>, 
+        mut unsendable: mpsc::UnboundedReceiver<Unsendable>,
+    ) {
+        let mut linked = true;
+        loop{
+            tokio::task::yield_now().await;
+            let sx_clone = self.sx.clone();
+            let idle = self.idle_deadline();
+            
+            tokio::select! {
+                biased;
+                // a Client keeps collecting for its calls, even if its Collector was dropped
+                _ = sx_clone.closed(), if self.calls.is_none() => { return }
+                reason = &mut closed, if linked => match reason {
+                    Ok(reason) => return self.send_event(Event::Disconnect(reason)).await,
+                    // the Emitter is gone, but the peer may still be sending
+                    Err(_) => linked = false,
+                },
+                Some(unsendable) = unsendable.recv() => self.unsendable(unsendable).await,
+                _ = tokio::time::sleep_until(idle.unwrap_or(self.last_activity)), if idle.is_some() => {
+                    return self.send_event(Event::Disconnect(DisconnectEvent::Timeout)).await
+                }
+                _ = tokio::time::sleep_until(self.next_ping), if self.ping.is_none() && self.settings.heartbeat_interval.is_some() => {
+                    self.ping()
+                }
+                _ = tokio::time::sleep_until(self.ping_deadline()), if self.ping.is_some() => {
+                    return self.send_event(Event::Disconnect(DisconnectEvent::Unresponsive)).await
+                }
+
+                data = self.collect_data() => {
+                    match data {
+                        Ok(Status::Continue) => (),
+                        Ok(Status::Disconnect(reason)) => return self.send_event(Event::Disconnect(reason)).await,
+                        Err(err) => match err.kind() {
+                            ErrorKind::WouldBlock | ErrorKind::Interrupted => (),
+                            // reading again would most likely fail the same way
+                            _ => return self.send_event(Event::Disconnect(DisconnectEvent::IoError(Arc::new(err)))).await,
+                        },
+                    }
+                }
+            };
+        }
+    }
+
+    fn ping(&mut self) {
+        let ping = self.ping_count;
+        self.ping_count += 1;
+        self.ping = Some((ping, Instant::now()));
+        self.emit(Outgoing::Ping(ping));
+    }
+
+    /// `None` if the timeout is too long to ever be reached.
+    fn idle_deadline(&self) -> Option<Instant> {
+        self.last_activity.checked_add(self.settings.timeout)
+    }
+
+    fn ping_deadline(&self) -> Instant {
+        match self.ping {
+            Some((_, sent)) => sent + self.settings.heartbeat_timeout,
+            None => Instant::now(),
+        }
+    }
+
+    fn pong(&mut self, ping: u64) -> Delivery<Msg> {
+        match self.ping {
+            Some((expected, sent)) if expected == ping => {
+                self.ping = None;
+                self.next_ping = sent + self.settings.heartbeat_interval.unwrap_or_default();
+                Some((Event::Rtt(sent.elapsed()), self.id))
+            },
+            // an answer to a ping we already gave up on
+            _ => None,
+        }
+    }
+
+    /// Sends a heartbeat through the Emitter of this connection, unless it is already gone.
+    /// Skips the queued Messages, so a slow peer doesnt look unresponsive.
+    fn emit(&mut self, out: Outgoing<Out>) {
+        if let Some(emitter) = self.emitter.upgrade() {
+            emitter.send_control(out).ok();
+        }
     }
 
     async fn send_event(&mut self, event: Event<Msg>) {
@@ -58,45 +195,163 @@ impl<Msg: Message> Collector<Msg>{
         self.sx.send((event, self.id)).await.ok();
     }
 
+    /// A call which couldnt be sent fails right away, anything else is reported.
+    async fn unsendable(&mut self, unsendable: Unsendable) {
+        match (unsendable.call, &self.calls) {
+            (Some(call), Some(calls)) => calls.complete(call, Err(CallError::Failed(unsendable.err.to_string()))),
+            _ => self.send_event(Event::EncodeError(unsendable.err.into())).await,
+        }
+    }
+
+    fn illegal(&self, payload: BytesMut, err: codec::Error) -> Delivery<Msg> {
+        Some((Event::IllegalData((payload.to_vec(), err).into()), self.id))
+    }
+
     async fn collect_data(&mut self) -> io::Result<Status> {
-        self.stream.readable().await?;
+        // whatever is still buffered goes first, our last delivery might have been interrupted
+        if let status @ Status::Disconnect(_) = self.decode_loop().await {
+            return Ok(status)
+        }
 
-        let bytes_read = self.stream.try_read_buf(&mut self.buffer)?;
+        self.buffer.reserve(READ_CHUNK);
+        let bytes_read = self.stream.read_buf(&mut self.buffer).await?;
         
         match bytes_read {
-            0 => Ok(Status::Finish),
-            _ => {
-                self.decode_loop().await;
-                Ok(Status::Continue)
-            },
+            0 => Ok(Status::Disconnect(DisconnectEvent::Clean)),
+            _ => Ok(self.decode_loop().await),
         }
     }
 
-    async fn decode_loop(&mut self) {
-        loop {
-            match bincode::decode_from_reader::<Msg,_,_>(&mut self.buffer, self.config){
-                Ok(msg) => {
-                    self.buffer.fwd();
-                    self.send_event(msg.into()).await;
-                },
-                Err(err) => {
-                    match err{
-                        DecodeError::UnexpectedEnd{..} => {
-                            self.buffer.back();
-                            return
-                        }
-                        _ => {
-                            self.buffer.clear();
-                            self.send_event(Illegal{vec: Vec::new(), err: err.into()}.into()).await;
-                        }
-                    }
+    /// Decodes every buffered frame. This may be cancelled at any await, so the 
+    /// room for an event is reserved before its frame leaves the buffer.
+    async fn decode_loop(&mut self) -> Status {
+        while self.frame_ready() {
+            // `None` if the Collector was dropped, the frame is still handled
+            let permit = self.sx.clone().reserve_owned().await.ok();
+
+            let delivery = match self.next_frame() {
+                Ok((header, payload)) => match self.decode(header, payload) {
+                    Ok(delivery) => delivery,
+                    Err(status) => return status,
                 },
+                Err(oversized) => Some((Event::Oversized(oversized), self.id)),
             };
+            if let (Some(permit), Some(delivery)) = (permit, delivery) {
+                permit.send(delivery);
+            }
         }
+
+        Status::Continue
+    }
+
+    /// Handles a single frame. Returns the event it turned into, if any.
+    fn decode(&mut self, header: Header, payload: BytesMut) -> Result<Delivery<Msg>, Status> {
+        if matches!(header.kind, Kind::Message | Kind::Call | Kind::Reply | Kind::Failed) {
+            self.last_activity = Instant::now();
+        }
+
+        let delivery = match (header.kind, self.id, self.calls.clone()) {
+            (Kind::Message, _, _) => match self.codec.decode(&payload) {
+                Ok(msg) => Some((Event::Message(msg), self.id)),
+                Err(err) => self.illegal(payload, err),
+            },
+            (Kind::Call, Origin::Id(id), _) => match frame::split_id(payload) {
+                Ok((call, payload)) => match self.codec.decode(&payload) {
+                    Ok(msg) => Some((Event::Message(msg), Origin::Call(id, call))),
+                    Err(err) => self.illegal(payload, err),
+                },
+                Err(_) => return Err(Status::violation("call frame without call id")),
+            },
+            (Kind::Reply, _, Some(calls)) => match frame::split_id(payload) {
+                Ok((call, payload)) => match self.codec.decode(&payload) {
+                    Ok(msg) => { calls.complete(call, Ok(msg)); None },
+                    Err(err) => { calls.complete(call, Err(CallError::IllegalData(Illegal::from((payload.to_vec(), err))))); None },
+                },
+                Err(_) => return Err(Status::violation("reply frame without call id")),
+            },
+            (Kind::Failed, _, Some(calls)) => match frame::split_id(payload) {
+                Ok((call, reason)) => { 
+                    calls.complete(call, Err(CallError::Failed(String::from_utf8_lossy(&reason).into_owned()))); 
+                    None 
+                },
+                Err(_) => return Err(Status::violation("failed frame without call id")),
+            },
+            (Kind::Ping, _, _) => match frame::split_id(payload) {
+                Ok((ping, _)) => { self.emit(Outgoing::Pong(ping)); None },
+                Err(_) => return Err(Status::violation("ping frame without ping id")),
+            },
+            (Kind::Pong, _, _) => match frame::split_id(payload) {
+                Ok((ping, _)) => self.pong(ping),
+                Err(_) => return Err(Status::violation("pong frame without ping id")),
+            },
+            (Kind::Close, id, _) => return Err(match (frame::split_close(payload), id) {
+                (Some((code, reason)), Origin::OnClient) => Status::Disconnect(frame::close_reason(code, reason)),
+                (Some((code, reason)), _) => Status::Disconnect(DisconnectEvent::PeerClosed{ code, reason }),
+                (None, _) => Status::violation("malformed close frame"),
+            }),
+            // only this subscription is skipped, the connection is fine
+            (Kind::Subscribe, Origin::Id(_), _) => match frame::split_topic(&payload) {
+                Ok(pattern) => Some((Event::Subscribe(pattern), self.id)),
+                Err(err) => self.illegal(payload, err),
+            },
+            (Kind::Unsubscribe, Origin::Id(_), _) => match frame::split_topic(&payload) {
+                Ok(pattern) => Some((Event::Unsubscribe(pattern), self.id)),
+                Err(err) => self.illegal(payload, err),
+            },
+            (Kind::Call, _, _) => return Err(Status::violation("call frame sent to a client")),
+            (Kind::Subscribe | Kind::Unsubscribe, _, _) => return Err(Status::violation("subscription sent to a client")),
+            (Kind::Reply | Kind::Failed, _, _) => return Err(Status::violation("reply frame sent to the server")),
+            (Kind::Unknown(_), _, _) => self.illegal(payload, "unknown frame kind".into()),
+        };
+
+        Ok(delivery)
+    }
+
+    /// Whether `next_frame` has something to return. Throws away what is left of an oversized frame.
+    fn frame_ready(&mut self) -> bool {
+        if self.discard > 0 {
+            let cnt = self.discard.min(self.buffer.len());
+            self.buffer.advance(cnt);
+            self.discard -= cnt;
+            if self.discard > 0 { return false }
+        }
+
+        if self.buffer.len() < HEADER_LEN {
+            return false
+        }
+        let header = Header::parse(self.buffer[..HEADER_LEN].try_into().expect("we checked the length above"));
+
+        if header.len <= self.settings.max_frame_size && self.buffer.len() < HEADER_LEN + header.len {
+            self.buffer.reserve(HEADER_LEN + header.len - self.buffer.len());
+            return false
+        }
+        true
+    }
+
+    /// Takes the next frame out of the buffer, once `frame_ready`. Frames larger 
+    /// than `max_frame_size` are skipped without ever being buffered.
+    fn next_frame(&mut self) -> Result<(Header, BytesMut), Oversized> {
+        let header = Header::parse(self.buffer[..HEADER_LEN].try_into().expect("checked by frame_ready"));
+        self.buffer.advance(HEADER_LEN);
+
+        if header.len > self.settings.max_frame_size {
+            self.discard = header.len;
+            return Err(Oversized{ len: header.len, max: self.settings.max_frame_size })
+        }
+        Ok((header, self.buffer.split_to(header.len)))
     }
 }
 
+/// An event and where it came from.
+type Delivery<Msg> = Option<(Event<Msg>, Origin)>;
+
 enum Status {
-    Finish,
     Continue,
+    Disconnect(DisconnectEvent),
+}
+
+impl Status {
+    fn violation(what: &'static str) -> Self {
+        Status::Disconnect(DisconnectEvent::ProtocolViolation(what))
+    }
 }
\ No newline at end of file
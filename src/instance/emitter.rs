@@ -1,53 +1,176 @@
-use std::io::{self, ErrorKind};
+use std::{io::{self, IoSlice}, sync::Arc};
 
-use tokio::{net::tcp::OwnedWriteHalf, sync::mpsc};
+use bytes::Bytes;
+use tokio::{io::AsyncWriteExt, sync::{mpsc, oneshot}, task::JoinHandle, time::Instant};
 
-use crate::Message;
+use crate::{Message, codec::Codec, event::DisconnectEvent, transport::FlushPolicy};
 
-pub struct Emitter<Msg>{
-    stream: OwnedWriteHalf,
-    rx: mpsc::Receiver<Msg>,
+use super::{WriteHalf, frame::{self, Outgoing}, queue, collector::{Report, Unsendable}};
+
+/// At most this many frames are written at once.
+const MAX_BATCH: usize = 64;
+/// No more frames are added to a batch once it is this large.
+const MAX_BATCH_BYTES: usize = 64 * 1024;
+
+pub struct Emitter<Msg, C>{
+    stream: WriteHalf,
+    rx: queue::Receiver<Outgoing<Msg>>,
+    codec: C,
+    flush: FlushPolicy,
+    /// Tells the Collector of the same connection that we closed it, or that it broke.
+    closed: Option<oneshot::Sender<DisconnectEvent>>,
+    /// Tells the Collector about Messages which couldnt be encoded.
+    unsendable: mpsc::UnboundedSender<Unsendable>,
+    /// Encoded frames waiting to be written.
+    batch: Vec<Bytes>,
+    batch_bytes: usize,
 }
 
-impl<Msg: Message> Emitter<Msg> {
+impl<Msg: Message, C: Codec<Msg>> Emitter<Msg, C> {
+    /// `report` is the other end of the `Link` of the Collector.
     pub fn spawn_on_task(
-        stream: OwnedWriteHalf, 
-        rx: mpsc::Receiver<Msg>,
-    ) {
-        Emitter{stream, rx}.emit_loop();
+        stream: WriteHalf, 
+        rx: queue::Receiver<Outgoing<Msg>>,
+        codec: C,
+        flush: FlushPolicy,
+        report: Report,
+    ) -> JoinHandle<()> {
+        let Report{ closed, unsendable } = report;
+        Emitter{stream, rx, codec, flush, closed: Some(closed), unsendable, batch: Vec::new(), batch_bytes: 0}.emit_loop()
     }
 
-    fn emit_loop(mut self) {
+    fn emit_loop(mut self) -> JoinHandle<()> {
         tokio::spawn(async move{
             loop{
                 tokio::task::yield_now().await;
-                let msg = match self.rx.recv().await{
-                    Some(msg) => msg,
+                let msg = tokio::select! {
+                    biased;
+                    _ = self.rx.killed() => return self.report(DisconnectEvent::SlowConsumer),
+                    msg = self.rx.recv() => match msg {
+                        Some(msg) => msg,
 
-                    // this happens when the mpsc::sender is dropped - we simply end the loop
-                    None => return,
+                        // this happens when every sender is dropped - we close the stream and end the loop
+                        None => { self.stream.shutdown().await.ok(); return },
+                    },
                 };
 
-                if let Err(e) = self.respond(msg).await{
-                    match e.kind() {
-                        ErrorKind::WouldBlock => continue,
-
-                        // this should never happen?
-                        ErrorKind::BrokenPipe => panic!("In sender: {}", e),
-                        _ => panic!("{}", e)
-                    }
+                let close = self.collect_batch(msg).await;
+                let batch = self.take_batch();
+                let written = tokio::select! {
+                    biased;
+                    // the peer doesnt read fast enough, so this write might never finish
+                    _ = self.rx.killed() => return self.report(DisconnectEvent::SlowConsumer),
+                    written = write_batch(&mut self.stream, &batch) => written,
+                };
+                if let Err(e) = written {
+                    // the Collector reports the broken connection
+                    return self.report(DisconnectEvent::IoError(Arc::new(e)))
                 };
+
+                if let Some((code, reason)) = close {
+                    return self.close(code, reason).await
+                }
+            }
+        })
+    }
+
+    /// Adds `first` and whatever else is queued to the batch, waiting for more 
+    /// if the FlushPolicy says so. Stops at a close frame and returns it.
+    async fn collect_batch(&mut self, first: Outgoing<Msg>) -> Option<(u16, String)> {
+        let deadline = match self.flush {
+            FlushPolicy::Immediate => None,
+            FlushPolicy::Batched{ max_delay } => Some(Instant::now() + max_delay),
+        };
+
+        let mut next = first;
+        loop{
+            match next {
+                Outgoing::Close(code, reason) => return Some((code, reason)),
+                msg => self.push(msg),
             }
-        });
+            if self.batch.len() >= MAX_BATCH || self.batch_bytes >= MAX_BATCH_BYTES {
+                return None
+            }
+
+            next = match (self.rx.try_recv(), deadline) {
+                (Some(msg), _) => msg,
+                (None, Some(deadline)) => match tokio::time::timeout_at(deadline, self.rx.recv()).await {
+                    Ok(Some(msg)) => msg,
+                    _ => return None,
+                },
+                (None, None) => return None,
+            };
+        }
+    }
+
+    /// The connection survives a Message which cant be encoded, only that Message is lost.
+    fn push(&mut self, msg: Outgoing<Msg>) {
+        match (frame::encode(&msg, &self.codec), msg) {
+            (Ok(bin), _) => { self.batch_bytes += bin.len(); self.batch.push(bin) },
+            // the caller would wait for its timeout otherwise
+            (Err(err), Outgoing::Reply(call, _)) => self.push(Outgoing::Failed(call, err.to_string())),
+            (Err(err), Outgoing::Call(call, _)) => { self.unsendable.send(Unsendable{ call: Some(call), err }).ok(); },
+            (Err(err), _) => { self.unsendable.send(Unsendable{ call: None, err }).ok(); },
+        }
+    }
+
+    fn take_batch(&mut self) -> Vec<Bytes> {
+        self.batch_bytes = 0;
+        std::mem::take(&mut self.batch)
+    }
+
+    /// Sends the close frame after everything queued before it, then shuts the stream down.
+    async fn close(&mut self, code: u16, reason: String) {
+        self.push(Outgoing::Close(code, reason.clone()));
+        let batch = self.take_batch();
+        write_batch(&mut self.stream, &batch).await.ok();
+        self.stream.shutdown().await.ok();
+
+        self.report(frame::close_reason(code, reason));
+    }
+
+    fn report(&mut self, reason: DisconnectEvent) {
+        if let Some(closed) = self.closed.take() {
+            closed.send(reason).ok();
+        }
+    }
+}
+
+/// Writes every frame of the batch completely, then flushes.
+async fn write_batch(stream: &mut WriteHalf, batch: &[Bytes]) -> io::Result<()> {
+    match (batch.len(), stream.is_write_vectored()) {
+        (0, _) => return Ok(()),
+        (1, _) => stream.write_all(&batch[0]).await?,
+        (_, true) => write_all_vectored(stream, batch).await?,
+        // streams without vectored writes would write one frame at a time
+        (_, false) => stream.write_all(&batch.concat()).await?,
     }
+    stream.flush().await
+}
+
+/// Like `write_all`, but for a vectored write. A write can stop anywhere, 
+/// even in the middle of a frame.
+async fn write_all_vectored(stream: &mut WriteHalf, frames: &[Bytes]) -> io::Result<()> {
+    let (mut frame, mut offset) = (0, 0);
 
-    async fn respond(&self, msg: Msg) -> io::Result<()> {
-        let config = bincode::config::standard();
-        let bin = bincode::encode_to_vec(msg, config).expect("how did this go wrong?");
+    while frame < frames.len() {
+        let slices: Vec<IoSlice> = std::iter::once(&frames[frame][offset..])
+            .chain(frames[frame + 1..].iter().map(|f| &f[..]))
+            .map(IoSlice::new)
+            .collect();
 
-        self.stream.writable().await?;
-        self.stream.try_write(&bin)?;
-    
-        Ok(())
+        let mut written = stream.write_vectored(&slices).await?;
+        if written == 0 {
+            return Err(io::ErrorKind::WriteZero.into())
+        }
+
+        while frame < frames.len() && written >= frames[frame].len() - offset {
+            written -= frames[frame].len() - offset;
+            frame += 1;
+            offset = 0;
+        }
+        offset += written;
     }
-}
\ No newline at end of file
+
+    Ok(())
+}
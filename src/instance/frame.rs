@@ -0,0 +1,227 @@
+//! The wire format. Every frame is a fixed size header followed by its payload:
+//!
+//! | bytes | content                                   |
+//! |-------|-------------------------------------------|
+//! | 0..4  | payload length as a big-endian `u32`      |
+//! | 4     | frame kind                                |
+//! | 5..   | payload                                   |
+//!
+//! The payload of `Call` and `Reply` frames starts with a big-endian `u64` call id,
+//! followed by the encoded message. `Ping` and `Pong` frames only contain a big-endian 
+//! `u64` ping id. A `Close` frame contains a big-endian `u16` close code followed by
+//! a UTF-8 reason, and is the last frame on a connection. `Subscribe` and `Unsubscribe`
+//! frames only contain a UTF-8 topic pattern. A `Failed` frame answers a `Call` whose
+//! response couldnt be encoded, with the call id followed by a UTF-8 reason. Frames of
+//! an unknown kind are reported as `IllegalData` and skipped, which keeps the stream in sync.
+
+use bytes::{Buf, Bytes, BytesMut};
+
+use crate::{codec::{self, Codec}, event::DisconnectEvent};
+
+/// Size of the frame header in bytes.
+pub(crate) const HEADER_LEN: usize = 5;
+
+/// Size of a call or ping id in bytes.
+const ID_LEN: usize = 8;
+
+/// Size of a close code in bytes.
+const CODE_LEN: usize = 2;
+
+/// Close code of a Client which was kicked by the Server.
+pub(crate) const CLOSE_KICKED: u16 = 1;
+/// Close code of a Server which is shutting down.
+pub(crate) const CLOSE_SHUTDOWN: u16 = 2;
+
+/// The kind of a frame, sent as the 5th header byte.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Kind{
+    /// The payload is an encoded `Message`.
+    Message,
+    /// The payload is a call id and a request, which expects a `Reply`.
+    Call,
+    /// The payload is a call id and the response to that `Call`.
+    Reply,
+    /// A heartbeat, which expects a `Pong` with the same ping id.
+    Ping,
+    /// The answer to a `Ping`.
+    Pong,
+    /// The payload is a close code and a reason. Ends the connection.
+    Close,
+    /// The payload is a topic pattern the Client wants Responses for.
+    Subscribe,
+    /// The payload is a topic pattern the Client subscribed to before.
+    Unsubscribe,
+    /// The payload is a call id and why its response couldnt be sent.
+    Failed,
+    /// Anything we dont know about.
+    Unknown(u8),
+}
+
+impl From<u8> for Kind {
+    fn from(b: u8) -> Self {
+        match b {
+            0 => Kind::Message,
+            1 => Kind::Call,
+            2 => Kind::Reply,
+            3 => Kind::Ping,
+            4 => Kind::Pong,
+            5 => Kind::Close,
+            6 => Kind::Subscribe,
+            7 => Kind::Unsubscribe,
+            8 => Kind::Failed,
+            b => Kind::Unknown(b),
+        }
+    }
+}
+
+impl From<Kind> for u8 {
+    fn from(k: Kind) -> Self {
+        match k {
+            Kind::Message => 0,
+            Kind::Call => 1,
+            Kind::Reply => 2,
+            Kind::Ping => 3,
+            Kind::Pong => 4,
+            Kind::Close => 5,
+            Kind::Subscribe => 6,
+            Kind::Unsubscribe => 7,
+            Kind::Failed => 8,
+            Kind::Unknown(b) => b,
+        }
+    }
+}
+
+/// A parsed frame header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Header{
+    pub len: usize,
+    pub kind: Kind,
+}
+
+impl Header {
+    pub fn parse(bytes: [u8; HEADER_LEN]) -> Self {
+        let len = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize;
+        Header{ len, kind: bytes[4].into() }
+    }
+
+    fn write(&self, buf: &mut [u8]) {
+        buf[..4].copy_from_slice(&(self.len as u32).to_be_bytes());
+        buf[4] = self.kind.into();
+    }
+}
+
+/// A `Message` on its way onto the stream.
+#[derive(Debug)]
+pub(crate) enum Outgoing<Msg>{
+    /// A plain Message.
+    Msg(Msg),
+    /// A request which expects a `Reply` with the same call id.
+    #[cfg_attr(not(feature = "client"), allow(dead_code))]
+    Call(u64, Msg),
+    /// The response to a `Call`.
+    #[cfg_attr(not(feature = "server"), allow(dead_code))]
+    Reply(u64, Msg),
+    /// A heartbeat with its ping id.
+    Ping(u64),
+    /// The answer to the `Ping` with this id.
+    Pong(u64),
+    /// Ends the connection with a close code and a reason.
+    Close(u16, String),
+    /// Subscribes to a topic pattern.
+    #[cfg_attr(not(feature = "client"), allow(dead_code))]
+    Subscribe(String),
+    /// Unsubscribes from a topic pattern.
+    #[cfg_attr(not(feature = "client"), allow(dead_code))]
+    Unsubscribe(String),
+    /// Answers a `Call` whose response couldnt be encoded.
+    #[cfg_attr(not(feature = "server"), allow(dead_code))]
+    Failed(u64, String),
+    /// A complete frame, encoded once and shared between many Emitters.
+    #[cfg_attr(not(feature = "broadcast"), allow(dead_code))]
+    Frame(Bytes),
+}
+
+impl<Msg> Outgoing<Msg> {
+    /// Only plain Messages may be dropped for a slow Client. Losing a reply or 
+    /// a heartbeat would fail a call or the whole connection.
+    #[cfg_attr(not(feature = "server"), allow(dead_code))]
+    pub fn droppable(&self) -> bool {
+        matches!(self, Outgoing::Msg(_) | Outgoing::Frame(_))
+    }
+}
+
+/// Encodes an `Outgoing` Message into a complete frame.
+pub(crate) fn encode<Msg, C: Codec<Msg>>(out: &Outgoing<Msg>, codec: &C) -> Result<Bytes, codec::Error> {
+    let mut buf = vec![0; HEADER_LEN];
+    let kind = match out {
+        Outgoing::Msg(msg) => { codec.encode(msg, &mut buf)?; Kind::Message },
+        Outgoing::Call(call, msg) => { buf.extend_from_slice(&call.to_be_bytes()); codec.encode(msg, &mut buf)?; Kind::Call },
+        Outgoing::Reply(call, msg) => { buf.extend_from_slice(&call.to_be_bytes()); codec.encode(msg, &mut buf)?; Kind::Reply },
+        Outgoing::Ping(ping) => { buf.extend_from_slice(&ping.to_be_bytes()); Kind::Ping },
+        Outgoing::Pong(ping) => { buf.extend_from_slice(&ping.to_be_bytes()); Kind::Pong },
+        Outgoing::Close(code, reason) => { 
+            buf.extend_from_slice(&code.to_be_bytes()); 
+            buf.extend_from_slice(reason.as_bytes()); 
+            Kind::Close 
+        },
+        Outgoing::Failed(call, reason) => { 
+            buf.extend_from_slice(&call.to_be_bytes()); 
+            buf.extend_from_slice(reason.as_bytes()); 
+            Kind::Failed 
+        },
+        Outgoing::Subscribe(pattern) => { buf.extend_from_slice(pattern.as_bytes()); Kind::Subscribe },
+        Outgoing::Unsubscribe(pattern) => { buf.extend_from_slice(pattern.as_bytes()); Kind::Unsubscribe },
+        Outgoing::Frame(frame) => return Ok(frame.clone()),
+    };
+
+    let len = buf.len() - HEADER_LEN;
+    if len > u32::MAX as usize {
+        return Err("message is too large for a single frame".into())
+    }
+    Header{ len, kind }.write(&mut buf);
+
+    Ok(buf.into())
+}
+
+/// Splits the call or ping id off the payload of a `Call`, `Reply`, `Failed`, `Ping` or `Pong` frame.
+pub(crate) fn split_id(mut payload: BytesMut) -> Result<(u64, BytesMut), BytesMut> {
+    if payload.len() < ID_LEN {
+        return Err(payload)
+    }
+    let id = payload.get_u64();
+    Ok((id, payload))
+}
+
+/// Splits the payload of a `Close` frame into its close code and reason.
+pub(crate) fn split_close(mut payload: BytesMut) -> Option<(u16, String)> {
+    if payload.len() < CODE_LEN {
+        return None
+    }
+    let code = payload.get_u16();
+    let reason = String::from_utf8(payload.to_vec()).ok()?;
+    Some((code, reason))
+}
+
+/// Reads the topic pattern of a `Subscribe` or `Unsubscribe` frame.
+pub(crate) fn split_topic(payload: &[u8]) -> Result<String, codec::Error> {
+    let pattern = String::from_utf8(payload.to_vec())?;
+    match valid_pattern(&pattern) {
+        true => Ok(pattern),
+        false => Err("invalid topic pattern".into()),
+    }
+}
+
+/// Whether a topic pattern can be subscribed to: segments separated by `.`, none 
+/// of them empty, and a `*` only ever as a whole segment.
+pub(crate) fn valid_pattern(pattern: &str) -> bool {
+    pattern.split('.').all(|segment| !segment.is_empty() && (segment == "*" || !segment.contains('*')))
+}
+
+/// What a close frame sent by the Server means for a Client.
+pub(crate) fn close_reason(code: u16, reason: String) -> DisconnectEvent {
+    match code {
+        CLOSE_KICKED => DisconnectEvent::KickedByServer{ reason },
+        CLOSE_SHUTDOWN => DisconnectEvent::ServerShutdown,
+        code => DisconnectEvent::PeerClosed{ code, reason },
+    }
+}
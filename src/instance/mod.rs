@@ -1,6 +1,27 @@
+mod calls;
 mod collector;
 mod emitter;
-mod ring_buffer;
+mod frame;
+pub(crate) mod queue;
+#[cfg(feature = "websocket")]
+mod websocket;
 
-pub(crate) use collector::Collector;
+pub(crate) use calls::{Calls, CallError};
+pub(crate) use collector::{Collector, Settings, Link};
 pub(crate) use emitter::Emitter;
+pub(crate) use frame::Outgoing;
+#[cfg(feature = "client")]
+pub(crate) use frame::valid_pattern;
+#[cfg(feature = "broadcast")]
+pub(crate) use frame::encode;
+#[cfg(feature = "server")]
+pub(crate) use frame::{CLOSE_KICKED, CLOSE_SHUTDOWN};
+#[cfg(feature = "websocket")]
+pub(crate) use websocket::WsStream;
+
+use tokio::io::{AsyncRead, AsyncWrite};
+
+/// The read half of any kind of connection.
+pub(crate) type ReadHalf = Box<dyn AsyncRead + Unpin + Send>;
+/// The write half of any kind of connection.
+pub(crate) type WriteHalf = Box<dyn AsyncWrite + Unpin + Send>;
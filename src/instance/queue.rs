@@ -0,0 +1,267 @@
+use std::{collections::VecDeque, fmt, pin::pin, sync::{Arc, Mutex, MutexGuard, Weak}};
+
+use tokio::sync::Notify;
+
+/// A bounded queue from the senders of a connection to its Emitter. Works like a
+/// `tokio::sync::mpsc` channel, but the senders can also drop the oldest entry
+/// or make the Emitter give up, which the `BackpressurePolicy` needs.
+pub(crate) fn channel<T>(cap: usize) -> (Sender<T>, Receiver<T>) {
+    let shared = Arc::new(Shared{
+        state: Mutex::new(State{ 
+            items: VecDeque::with_capacity(cap), control: VecDeque::new(), senders: 1, closed: false, killed: false,
+        }),
+        cap: cap.max(1),
+        readable: Notify::new(),
+        writable: Notify::new(),
+        kill: Notify::new(),
+    });
+
+    (Sender{ shared: shared.clone() }, Receiver{ shared })
+}
+
+struct Shared<T>{
+    state: Mutex<State<T>>,
+    cap: usize,
+    readable: Notify,
+    writable: Notify,
+    kill: Notify,
+}
+
+struct State<T>{
+    items: VecDeque<T>,
+    /// Goes first and doesnt count towards the cap. See `Sender::send_control`.
+    control: VecDeque<T>,
+    senders: usize,
+    /// The Receiver is gone.
+    closed: bool,
+    killed: bool,
+}
+
+impl<T> Shared<T> {
+    fn lock(&self) -> MutexGuard<'_, State<T>> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+pub(crate) enum TrySendError<T>{
+    Full(T),
+    Closed(T),
+}
+
+pub(crate) struct Sender<T>{
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Sender<T> {
+    /// Waits for space in the queue. Gives the item back if the Receiver is gone.
+    pub async fn send(&self, mut item: T) -> Result<(), T> {
+        loop{
+            let mut writable = pin!(self.shared.writable.notified());
+            writable.as_mut().enable();
+
+            match self.try_send(item) {
+                Ok(()) => return Ok(()),
+                Err(TrySendError::Full(back)) => item = back,
+                Err(TrySendError::Closed(back)) => return Err(back),
+            }
+            writable.await;
+        }
+    }
+
+    pub fn try_send(&self, item: T) -> Result<(), TrySendError<T>> {
+        let mut state = self.shared.lock();
+        if state.closed {
+            return Err(TrySendError::Closed(item))
+        }
+        if state.items.len() >= self.shared.cap {
+            return Err(TrySendError::Full(item))
+        }
+        state.items.push_back(item);
+        drop(state);
+
+        self.shared.readable.notify_one();
+        Ok(())
+    }
+
+    /// Sends even if the queue is full, by dropping its oldest entry which is `evictable`.
+    /// Fails with `Full` if there is none.
+    #[cfg_attr(not(feature = "server"), allow(dead_code))]
+    pub fn force_send(&self, item: T, evictable: impl Fn(&T) -> bool) -> Result<(), TrySendError<T>> {
+        let mut state = self.shared.lock();
+        if state.closed {
+            return Err(TrySendError::Closed(item))
+        }
+        if state.items.len() >= self.shared.cap {
+            match state.items.iter().position(evictable) {
+                Some(oldest) => { state.items.remove(oldest); },
+                None => return Err(TrySendError::Full(item)),
+            }
+        }
+        state.items.push_back(item);
+        drop(state);
+
+        self.shared.readable.notify_one();
+        Ok(())
+    }
+
+    /// Queues a small control frame in front of everything else, even if the queue is full.
+    /// Otherwise it could wait forever behind a flood of `force_send`s.
+    pub fn send_control(&self, item: T) -> Result<(), T> {
+        let mut state = self.shared.lock();
+        if state.closed {
+            return Err(item)
+        }
+        state.control.push_back(item);
+        drop(state);
+
+        self.shared.readable.notify_one();
+        Ok(())
+    }
+
+    /// Queues the last item behind everything else, even if the queue is full. 
+    /// Only for a close frame, the Emitter stops there anyway.
+    #[cfg_attr(not(feature = "server"), allow(dead_code))]
+    pub fn send_last(&self, item: T) -> Result<(), T> {
+        let mut state = self.shared.lock();
+        if state.closed {
+            return Err(item)
+        }
+        state.items.push_back(item);
+        drop(state);
+
+        self.shared.readable.notify_one();
+        Ok(())
+    }
+
+    /// Makes the Emitter give up, even in the middle of a write.
+    #[cfg_attr(not(feature = "server"), allow(dead_code))]
+    pub fn kill(&self) {
+        self.shared.lock().killed = true;
+        self.shared.kill.notify_one();
+    }
+
+    /// Takes everything still queued, except control frames. Works after the Receiver is gone too.
+    #[cfg_attr(not(feature = "client"), allow(dead_code))]
+    pub fn take_unsent(&self) -> VecDeque<T> {
+        let items = std::mem::take(&mut self.shared.lock().items);
+        self.shared.writable.notify_waiters();
+        items
+    }
+
+    pub fn downgrade(&self) -> WeakSender<T> {
+        WeakSender{ shared: Arc::downgrade(&self.shared) }
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        self.shared.lock().senders += 1;
+        Sender{ shared: self.shared.clone() }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let mut state = self.shared.lock();
+        state.senders -= 1;
+        let last = state.senders == 0;
+        drop(state);
+
+        if last {
+            self.shared.readable.notify_one();
+        }
+    }
+}
+
+impl<T> fmt::Debug for Sender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Sender").field("cap", &self.shared.cap).finish_non_exhaustive()
+    }
+}
+
+/// Doesnt keep the queue open.
+pub(crate) struct WeakSender<T>{
+    shared: Weak<Shared<T>>,
+}
+
+impl<T> WeakSender<T> {
+    pub fn upgrade(&self) -> Option<Sender<T>> {
+        let shared = self.shared.upgrade()?;
+        let mut state = shared.lock();
+        if state.senders == 0 {
+            return None
+        }
+        state.senders += 1;
+        drop(state);
+
+        Some(Sender{ shared })
+    }
+}
+
+pub(crate) struct Receiver<T>{
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Receiver<T> {
+    /// Returns `None` once every Sender is gone and the queue is empty.
+    pub async fn recv(&self) -> Option<T> {
+        loop{
+            {
+                let mut state = self.shared.lock();
+                if let Some(item) = state.control.pop_front() {
+                    return Some(item)
+                }
+                if let Some(item) = state.items.pop_front() {
+                    drop(state);
+                    self.shared.writable.notify_waiters();
+                    return Some(item)
+                }
+                if state.senders == 0 {
+                    return None
+                }
+            }
+            self.shared.readable.notified().await;
+        }
+    }
+
+    pub fn try_recv(&self) -> Option<T> {
+        let mut state = self.shared.lock();
+        if let Some(item) = state.control.pop_front() {
+            return Some(item)
+        }
+        let item = state.items.pop_front()?;
+        drop(state);
+        self.shared.writable.notify_waiters();
+        Some(item)
+    }
+
+    /// Every Sender is gone, but there might still be items queued.
+    #[cfg_attr(not(feature = "client"), allow(dead_code))]
+    pub fn is_closed(&self) -> bool {
+        self.shared.lock().senders == 0
+    }
+
+    /// Resolves once a Sender called `kill`.
+    pub async fn killed(&self) {
+        loop{
+            let kill = self.shared.kill.notified();
+            if self.shared.lock().killed {
+                return
+            }
+            kill.await;
+        }
+    }
+}
+
+/// The queued items stay for `Sender::take_unsent`, until the last Sender is gone.
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        let control = {
+            let mut state = self.shared.lock();
+            state.closed = true;
+            std::mem::take(&mut state.control)
+        };
+        drop(control);
+        self.shared.writable.notify_waiters();
+    }
+}
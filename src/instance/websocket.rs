@@ -0,0 +1,78 @@
+use std::{io, pin::Pin, task::{Context, Poll, ready}};
+
+use bytes::{Bytes, BytesMut, Buf};
+use futures_util::{Sink, Stream};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio_tungstenite::{WebSocketStream, tungstenite::{self, Message}};
+
+/// Runs the byte stream of a connection over binary WebSocket messages. 
+/// Every flush sends the buffered bytes as one message, so a message carries 
+/// every frame of a batch written by an `instance::Emitter`. Reads ignore 
+/// message boundaries, the messages are just one stream of bytes.
+pub(crate) struct WsStream<S>{
+    inner: WebSocketStream<S>,
+    read: Bytes,
+    write: BytesMut,
+}
+
+impl<S> WsStream<S> {
+    pub fn new(inner: WebSocketStream<S>) -> Self {
+        WsStream{ inner, read: Bytes::new(), write: BytesMut::new() }
+    }
+}
+
+fn into_io(err: tungstenite::Error) -> io::Error {
+    match err {
+        tungstenite::Error::Io(e) => e,
+        e => io::Error::other(e),
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncRead for WsStream<S> {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            if !this.read.is_empty() {
+                let cnt = this.read.len().min(buf.remaining());
+                buf.put_slice(&this.read[..cnt]);
+                this.read.advance(cnt);
+                return Poll::Ready(Ok(()))
+            }
+
+            match ready!(Pin::new(&mut this.inner).poll_next(cx)) {
+                Some(Ok(Message::Binary(data))) => this.read = data,
+                Some(Ok(Message::Text(_))) => return Poll::Ready(Err(
+                    io::Error::new(io::ErrorKind::InvalidData, "received a text message")
+                )),
+                // pings are answered by tungstenite itself
+                Some(Ok(Message::Ping(_) | Message::Pong(_) | Message::Frame(_))) => continue,
+                Some(Ok(Message::Close(_))) | None => return Poll::Ready(Ok(())),
+                Some(Err(tungstenite::Error::ConnectionClosed | tungstenite::Error::AlreadyClosed)) => 
+                    return Poll::Ready(Ok(())),
+                Some(Err(e)) => return Poll::Ready(Err(into_io(e))),
+            }
+        }
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncWrite for WsStream<S> {
+    fn poll_write(self: Pin<&mut Self>, _: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        self.get_mut().write.extend_from_slice(buf);
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if !this.write.is_empty() {
+            ready!(Pin::new(&mut this.inner).poll_ready(cx)).map_err(into_io)?;
+            let data = this.write.split().freeze();
+            Pin::new(&mut this.inner).start_send(Message::Binary(data)).map_err(into_io)?;
+        }
+        Pin::new(&mut this.inner).poll_flush(cx).map_err(into_io)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.as_mut().poll_flush(cx))?;
+        Pin::new(&mut self.get_mut().inner).poll_close(cx).map_err(into_io)
+    }
+}
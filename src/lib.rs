@@ -1,7 +1,10 @@
-//! 
+//! A simple asynchronous server/client crate built on tokio for easy two-way streaming.
 
+pub mod codec;
 pub mod event;
+pub mod transport;
 pub use bincode::{Decode, Encode};
+pub use error::{Error, Result};
 
 #[cfg(feature = "server")]
 pub mod server;
@@ -9,25 +12,18 @@ pub mod server;
 #[cfg(feature = "client")]
 pub mod client;
 
-/// Any data structure implementing this can be transmitted. 
-/// Usually, `#[derive(Decode, Encode)]` will be enough to implement it. 
-/// If the broadcast feature is enabled, Clone is also required. 
-/// 
-/// Note: Debug is required for now. This will likely change in the future.
-#[cfg(feature = "broadcast")]
-pub trait Message:              Send + Clone + fmt::Debug + Encode + Decode + 'static{}
-#[cfg(feature = "broadcast")]
-impl<T> Message for T where T:  Send + Clone + fmt::Debug + Encode + Decode + 'static{}
+/// Re-export of the rustls version used by the tls feature.
+#[cfg(feature = "tls")]
+pub use tokio_rustls::rustls;
 
-/// Any data structure implementing this can be transmitted. 
-/// Usually, `#[derive(Decode, Encode)]` will be enough to implement it. 
-/// If the broadcast feature is enabled, Clone is also required. 
+/// Any data structure implementing this can be transmitted, as long as the 
+/// `Codec` in use supports it. For the default `Bincode` codec, 
+/// `#[derive(Decode, Encode)]` will be enough. 
 /// 
 /// Note: Debug is required for now. This will likely change in the future.
-#[cfg(not(feature = "broadcast"))]
-pub trait Message:              Send + fmt::Debug + Encode + Decode + 'static{}
-#[cfg(not(feature = "broadcast"))]
-impl<T> Message for T where T:  Send + fmt::Debug + Encode + Decode + 'static{}
+pub trait Message:              Send + fmt::Debug + 'static{}
+impl<T> Message for T where T:  Send + fmt::Debug + 'static{}
 
+mod error;
 mod instance;
 use std::fmt;
\ No newline at end of file
@@ -0,0 +1,65 @@
+use std::{any::{Any, TypeId}, collections::HashMap, fmt};
+
+/// Data the application attaches to a connection, e.g. a username or permissions. 
+/// Holds at most one value per type and is dropped once the Client disconnects.
+/// 
+/// See `Emitter::with_extensions`.
+#[derive(Default)]
+pub struct Extensions{
+    map: HashMap<TypeId, Box<dyn Any + Send + Sync>>,
+}
+
+impl Extensions {
+    /// An empty set of extensions.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stores a value, returning the one of the same type which was stored before.
+    pub fn insert<T: Send + Sync + 'static>(&mut self, value: T) -> Option<T> {
+        self.map.insert(TypeId::of::<T>(), Box::new(value))
+            .and_then(|old| old.downcast().ok().map(|old| *old))
+    }
+
+    /// The value of this type, if there is one.
+    pub fn get<T: Send + Sync + 'static>(&self) -> Option<&T> {
+        self.map.get(&TypeId::of::<T>()).and_then(|value| value.downcast_ref())
+    }
+
+    /// The value of this type, mutably, if there is one.
+    pub fn get_mut<T: Send + Sync + 'static>(&mut self) -> Option<&mut T> {
+        self.map.get_mut(&TypeId::of::<T>()).and_then(|value| value.downcast_mut())
+    }
+
+    /// Takes the value of this type out, if there is one.
+    pub fn remove<T: Send + Sync + 'static>(&mut self) -> Option<T> {
+        self.map.remove(&TypeId::of::<T>())
+            .and_then(|old| old.downcast().ok().map(|old| *old))
+    }
+
+    /// Whether a value of this type is stored.
+    pub fn contains<T: Send + Sync + 'static>(&self) -> bool {
+        self.map.contains_key(&TypeId::of::<T>())
+    }
+
+    /// How many values are stored.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Whether no value is stored.
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Drops every value.
+    pub fn clear(&mut self) {
+        self.map.clear()
+    }
+}
+
+impl fmt::Debug for Extensions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Extensions").field("len", &self.map.len()).finish_non_exhaustive()
+    }
+}
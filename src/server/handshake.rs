@@ -0,0 +1,49 @@
+use std::io;
+
+#[cfg(feature = "tls")]
+use tokio_rustls::TlsAcceptor;
+
+use crate::{instance, event::TransportKind, transport::Stream};
+
+/// What happens to a freshly accepted stream before it becomes a connection.
+#[derive(Clone)]
+pub(crate) enum Handshake{
+    Plain,
+    #[cfg(feature = "tls")]
+    Tls(TlsAcceptor),
+    #[cfg(feature = "websocket")]
+    WebSocket,
+}
+
+impl Handshake {
+    /// What the connection runs on after the handshake, if it changes that.
+    pub fn transport(&self) -> Option<TransportKind> {
+        match self {
+            Handshake::Plain => None,
+            #[cfg(feature = "tls")]
+            Handshake::Tls(_) => Some(TransportKind::Tls),
+            #[cfg(feature = "websocket")]
+            Handshake::WebSocket => Some(TransportKind::WebSocket),
+        }
+    }
+
+    pub async fn split<S: Stream>(self, stream: S) -> io::Result<(instance::ReadHalf, instance::WriteHalf)> {
+        match self {
+            Handshake::Plain => {
+                let (read, write) = tokio::io::split(stream);
+                Ok((Box::new(read), Box::new(write)))
+            },
+            #[cfg(feature = "tls")]
+            Handshake::Tls(acceptor) => {
+                let (read, write) = tokio::io::split(acceptor.accept(stream).await?);
+                Ok((Box::new(read), Box::new(write)))
+            },
+            #[cfg(feature = "websocket")]
+            Handshake::WebSocket => {
+                let stream = tokio_tungstenite::accept_async(stream).await.map_err(io::Error::other)?;
+                let (read, write) = tokio::io::split(instance::WsStream::new(stream));
+                Ok((Box::new(read), Box::new(write)))
+            },
+        }
+    }
+}
@@ -1,22 +1,41 @@
 //! Module for Server functionality. Enable the server feature to use it.
 
-use std::{io, time::Duration};
+use std::{io, time::Duration, marker::PhantomData};
+#[cfg(feature = "tls")]
+use std::sync::Arc;
+#[cfg(unix)]
+use std::path::Path;
 
-use tokio::{net::{TcpListener, ToSocketAddrs}, sync::mpsc};
-use crate::{Message, instance, event::{Origin, Event}};
+use tokio::{net::{ToSocketAddrs, TcpListener}, sync::{mpsc, oneshot}, time::Instant};
+#[cfg(unix)]
+use tokio::net::UnixListener;
+#[cfg(feature = "tls")]
+use tokio_rustls::{TlsAcceptor, rustls::ServerConfig};
+use crate::{Message, Error, Result, instance, event::{Origin, Event, ConnectionInfo, ClientId}, transport::{Listener, FlushPolicy}, codec::{Codec, Bincode}};
 
+mod extensions;
+mod handshake;
 mod pool;
+mod shutdown;
+#[cfg(feature = "broadcast")]
+mod topic;
+pub use extensions::Extensions;
+use handshake::Handshake;
 use pool::{PoolMessage, EmitterPool};
+use shutdown::{Tasks, Shutdown};
 
 #[derive(Debug)]
 /// A Server with an asynchronous full-duplex connection with every 
 /// Client. Can be into_split into an Emitter and Collector for async operations.
-pub struct Server<Req: Message, Res: Message>{
+/// 
+/// The `Codec` is picked through the Config and defaults to `Bincode`.
+pub struct Server<Req: Message, Res: Message, C = Bincode>{
     collector: Collector<Req, Res>,
     emitter: Emitter<Res>,
+    codec: PhantomData<C>,
 }
 
-impl<Req: Message, Res: Message> Server<Req, Res>{
+impl<Req: Message, Res: Message> Server<Req, Res> where Bincode: Codec<Req> + Codec<Res> {
     /// Initializes the accept loop, returning a Server with the default Config.
     pub async fn bind<I>(ip: I) -> io::Result<Server<Req, Res>>
         where I: ToSocketAddrs + Send + 'static,
@@ -24,40 +43,145 @@ impl<Req: Message, Res: Message> Server<Req, Res>{
         Self::bind_with_config(ip, Config::default()).await
     }
 
+    /// Initializes the accept loop on a Unix domain socket, returning a Server with 
+    /// the default Config. Fails if the path already exists.
+    #[cfg(unix)]
+    pub async fn bind_unix<P: AsRef<Path>>(path: P) -> io::Result<Server<Req, Res>> {
+        Self::bind_unix_with_config(path, Config::default()).await
+    }
+}
+
+impl<Req: Message, Res: Message, C: Codec<Req> + Codec<Res>> Server<Req, Res, C>{
     /// Initializes the accept loop, returning a Server. The Config can be customized.
-    pub async fn bind_with_config<I>(ip: I, config: Config) -> io::Result<Server<Req, Res>>
+    pub async fn bind_with_config<I>(ip: I, config: Config<C>) -> io::Result<Server<Req, Res, C>>
+        where I: ToSocketAddrs + Send + 'static,
+    {
+        Ok(Self::bind_inner(TcpListener::bind(ip).await?, Handshake::Plain, config))
+    }
+
+    /// Initializes the accept loop for TLS encrypted connections, returning a Server.
+    /// Enable the tls feature to use it.
+    #[cfg(feature = "tls")]
+    pub async fn bind_tls<I>(ip: I, config: Config<C>, tls: Arc<ServerConfig>) -> io::Result<Server<Req, Res, C>>
         where I: ToSocketAddrs + Send + 'static,
     {
+        Ok(Self::bind_inner(TcpListener::bind(ip).await?, Handshake::Tls(TlsAcceptor::from(tls)), config))
+    }
+
+    /// Initializes the accept loop for WebSocket connections, returning a Server. 
+    /// Every frame is sent as a binary WebSocket message. Enable the websocket feature to use it.
+    #[cfg(feature = "websocket")]
+    pub async fn bind_ws<I>(ip: I, config: Config<C>) -> io::Result<Server<Req, Res, C>>
+        where I: ToSocketAddrs + Send + 'static,
+    {
+        Ok(Self::bind_inner(TcpListener::bind(ip).await?, Handshake::WebSocket, config))
+    }
+
+    /// Initializes the accept loop on a Unix domain socket, returning a Server. 
+    /// The Config can be customized. Fails if the path already exists.
+    #[cfg(unix)]
+    pub async fn bind_unix_with_config<P: AsRef<Path>>(path: P, config: Config<C>) -> io::Result<Server<Req, Res, C>> {
+        Ok(Self::bind_inner(UnixListener::bind(path)?, Handshake::Plain, config))
+    }
+
+    /// Initializes the accept loop on a custom `Listener`, returning a Server. 
+    /// Use this for transports kumoko doesnt support out of the box.
+    /// Has to be called from within a tokio runtime.
+    pub fn from_listener<L: Listener>(listener: L, config: Config<C>) -> Server<Req, Res, C> {
+        Self::bind_inner(listener, Handshake::Plain, config)
+    }
+
+    fn bind_inner<L: Listener>(listener: L, handshake: Handshake, config: Config<C>) -> Server<Req, Res, C> {
         let (sx, rx) = mpsc::channel(config.collector_buffer);
-        let pool = EmitterPool::spawn_on_task(config.pool_buffer, config.client_buffer);
-        let listener = TcpListener::bind(ip).await?;
+        let (tasks, shutdown) = shutdown::tasks();
+        let pool = EmitterPool::spawn_on_task(config.pool_buffer, config.codec.clone(), config.backpressure, sx.clone(), &tasks);
     
-        accept_loop(listener, sx, pool.clone(), config.timeout)?;
-        let collector = Collector{rx, pool: pool.clone()};
+        accept_loop(listener, handshake, sx, pool.clone(), config, tasks);
+        let collector = Collector{rx, pool: pool.clone(), shutdown};
         let emitter = Emitter{pool};
     
-        Ok(Server{collector, emitter})
+        Server{collector, emitter, codec: PhantomData}
     }
 
     /// Gets the next event if one is available, otherwise it waits until it is.
-    pub async fn get_event(&mut self) -> (Event<Req>, Origin) {
+    pub async fn get_event(&mut self) -> Result<(Event<Req>, Origin)> {
         self.collector.get_event().await
     }
 
     /// Convenience method for applications which only care about requests.
-    pub async fn get_request(&mut self) -> (Req, Origin) {
+    pub async fn get_request(&mut self) -> Result<(Req, Origin)> {
         self.collector.get_request().await
     }
 
     /// Default method for streaming to Clients.
-    pub async fn emit_response(&self, res: Res, target: Target) {
-        self.emitter.emit_response(res, target).await;
+    pub async fn emit_response(&self, res: Res, target: Target) -> Result<()> {
+        self.emitter.emit_response(res, target).await
     }
 
     #[cfg(feature = "broadcast")]
     /// Broadcast to every connected Client.
-    pub async fn broadcast(&self, res: Res) {
-        self.emit_response(res, Target::All).await;
+    pub async fn broadcast(&self, res: Res) -> Result<()> {
+        self.emit_response(res, Target::All).await
+    }
+
+    /// Publishes to the subscribers of a topic. See `Emitter::publish`.
+    #[cfg(feature = "broadcast")]
+    pub async fn publish(&self, topic: &str, res: Res) -> Result<()> {
+        self.emitter.publish(topic, res).await
+    }
+
+    /// Every connected Client. See `Emitter::clients`.
+    pub async fn clients(&self) -> Result<Vec<(ClientId, ConnectionInfo)>> {
+        self.emitter.clients().await
+    }
+
+    /// How many Clients are connected. See `Emitter::connection_count`.
+    pub async fn connection_count(&self) -> Result<usize> {
+        self.emitter.connection_count().await
+    }
+
+    /// Information about a connected Client. See `Emitter::connection_info`.
+    pub async fn connection_info(&self, client: ClientId) -> Result<Option<ConnectionInfo>> {
+        self.emitter.connection_info(client).await
+    }
+
+    /// Works with the Extensions of a Client. See `Emitter::with_extensions`.
+    pub async fn with_extensions<T, F>(&self, client: ClientId, f: F) -> Result<Option<T>>
+        where T: Send + 'static, F: FnOnce(&mut Extensions) -> T + Send + 'static,
+    {
+        self.emitter.with_extensions(client, f).await
+    }
+
+    /// Gets a copy of an extension of a Client. See `Emitter::extension`.
+    pub async fn extension<T: Clone + Send + Sync + 'static>(&self, client: ClientId) -> Result<Option<T>> {
+        self.emitter.extension(client).await
+    }
+
+    /// Attaches an extension to a Client. See `Emitter::insert_extension`.
+    pub async fn insert_extension<T: Send + Sync + 'static>(&self, client: ClientId, value: T) -> Result<()> {
+        self.emitter.insert_extension(client, value).await
+    }
+
+    /// Adds a Client to a group. See `Emitter::join_group`.
+    #[cfg(feature = "broadcast")]
+    pub async fn join_group(&self, client: ClientId, group: GroupId) -> Result<()> {
+        self.emitter.join_group(client, group).await
+    }
+
+    /// Removes a Client from a group. See `Emitter::leave_group`.
+    #[cfg(feature = "broadcast")]
+    pub async fn leave_group(&self, client: ClientId, group: GroupId) -> Result<()> {
+        self.emitter.leave_group(client, group).await
+    }
+
+    /// Kicks Clients. See `Emitter::disconnect`.
+    pub async fn disconnect(&self, target: Target, reason: &str) -> Result<()> {
+        self.emitter.disconnect(target, reason).await
+    }
+
+    /// Shuts the Server down. See `Collector::shutdown`.
+    pub async fn shutdown(self, grace: Duration) -> ShutdownReport {
+        self.collector.shutdown(grace).await
     }
 
     /// Splits the Server into a Collector and a Emitter. The Emitter can be 
@@ -75,27 +199,80 @@ pub struct Collector<Req: Message, Res: Message>{
     rx: mpsc::Receiver<(Event<Req>, Origin)>,
     /// needs this to send disconnect messages to the pool
     pool: mpsc::Sender<PoolMessage<Res>>,
+    shutdown: Shutdown,
 }
 
 impl<Req: Message, Res: Message> Collector<Req, Res> {
     /// Gets the next event if one is available, otherwise it waits until it is.
-    pub async fn get_event(&mut self) -> (Event<Req>, Origin) {
-        let (e, o) = self.rx.recv().await.expect("while this owns a sender, the pool wont drop");
-        if let (Event::Disconnect(_), Origin::Id(id)) = (&e, o) {
-            self.pool.send(PoolMessage::Disconnect(id)).await.expect("while this owns a sender, the pool wont drop");
+    /// 
+    /// Fails with `Error::Closed` once nothing can produce events anymore.
+    pub async fn get_event(&mut self) -> Result<(Event<Req>, Origin)> {
+        let (e, o) = self.rx.recv().await.ok_or(Error::Closed)?;
+        let msg = match (&e, o) {
+            // the Emitter of this Client might already be gone
+            (Event::Disconnect(_), Origin::Id(id)) => Some(PoolMessage::Disconnect(id)),
+            #[cfg(feature = "broadcast")]
+            (Event::Subscribe(pattern), Origin::Id(id)) => Some(PoolMessage::Subscribe(id, pattern.clone())),
+            #[cfg(feature = "broadcast")]
+            (Event::Unsubscribe(pattern), Origin::Id(id)) => Some(PoolMessage::Unsubscribe(id, pattern.clone())),
+            _ => None,
+        };
+        if let Some(msg) = msg {
+            self.pool.send(msg).await.ok();
         }
 
-        (e, o)
+        Ok((e, o))
     }
 
     /// Convenience method for applications which only care about requests
-    pub async fn get_request(&mut self) -> (Req, Origin) {
+    pub async fn get_request(&mut self) -> Result<(Req, Origin)> {
         loop{
-            if let (Event::Message(msg), o) = self.get_event().await{
-                return (msg, o)
+            if let (Event::Message(msg), o) = self.get_event().await?{
+                return Ok((msg, o))
             }
         }
     }
+
+    /// Shuts the Server down. No new Clients are accepted, Responses which were 
+    /// already emitted are still sent, then every Client gets a close frame and sees 
+    /// `DisconnectEvent::ServerShutdown`. Connections which didnt close within 
+    /// the grace period are aborted.
+    /// 
+    /// Every Emitter does nothing afterwards.
+    pub async fn shutdown(mut self, grace: Duration) -> ShutdownReport {
+        let deadline = Instant::now() + grace;
+        let mut report = ShutdownReport::default();
+        self.shutdown.stop();
+
+        let (sx, clients) = oneshot::channel();
+        let told = async {
+            self.pool.send(PoolMessage::Shutdown(sx)).await.ok();
+            clients.await.unwrap_or_default()
+        };
+        let mut told = std::pin::pin!(told);
+        let mut open = true;
+
+        let done = loop{
+            tokio::select! {
+                biased;
+                clients = &mut told, if report.clients.is_none() => report.clients = Some(clients),
+                _ = self.shutdown.done() => break true,
+                _ = tokio::time::sleep_until(deadline) => break false,
+                event = self.rx.recv(), if open => match event {
+                    Some((Event::Message(_), _)) => report.dropped_requests += 1,
+                    Some(_) => (),
+                    None => open = false,
+                },
+            }
+        };
+
+        if !done {
+            self.shutdown.abort();
+            self.shutdown.done().await;
+        }
+        report.aborted = self.shutdown.aborted();
+        report
+    }
 }
 
 /// Lives on the main task
@@ -108,19 +285,108 @@ pub struct Emitter<Res>{
 }
 
 impl<Res: Message> Emitter<Res>{
-    /// Default method for streaming to Clients.
-    pub async fn emit_response(&self, res: Res, target: Target) {
-        self.pool.send(PoolMessage::Msg(res, target)).await.expect("while this owns a sender, the pool wont drop");
+    /// Default method for streaming to Clients. Responses to Clients which 
+    /// arent connected are dropped.
+    /// 
+    /// Fails with `Error::Closed` once the Server was shut down.
+    pub async fn emit_response(&self, res: Res, target: Target) -> Result<()> {
+        self.send(PoolMessage::Msg(res, target)).await
     }
 
     /// Broadcast to every connected Client.
     #[cfg(feature = "broadcast")]
-    pub async fn broadcast(&self, res: Res) {
-        self.emit_response(res, Target::All).await;
+    pub async fn broadcast(&self, res: Res) -> Result<()> {
+        self.emit_response(res, Target::All).await
+    }
+
+    /// Every connected Client with the `ConnectionInfo` it connected with, in no particular order.
+    /// 
+    /// A Client counts as connected until the Collector got its `Event::Disconnect`, 
+    /// or until it was kicked.
+    pub async fn clients(&self) -> Result<Vec<(ClientId, ConnectionInfo)>> {
+        let (sx, rx) = oneshot::channel();
+        self.send(PoolMessage::Clients(sx)).await?;
+        rx.await.map_err(|_| Error::Closed)
+    }
+
+    /// How many Clients are connected, see `clients`.
+    pub async fn connection_count(&self) -> Result<usize> {
+        let (sx, rx) = oneshot::channel();
+        self.send(PoolMessage::Count(sx)).await?;
+        rx.await.map_err(|_| Error::Closed)
+    }
+
+    /// The `ConnectionInfo` the Client connected with, or `None` if it isnt connected.
+    pub async fn connection_info(&self, client: ClientId) -> Result<Option<ConnectionInfo>> {
+        let (sx, rx) = oneshot::channel();
+        self.send(PoolMessage::Info(client, sx)).await?;
+        rx.await.map_err(|_| Error::Closed)
+    }
+
+    /// Runs `f` on the `Extensions` of a Client and returns its result, or `None` 
+    /// if the Client isnt connected. `f` runs on the task of the Server which 
+    /// sends every Response, so it should be quick.
+    /// 
+    /// The Extensions are dropped once the Collector got the `Event::Disconnect` of the Client.
+    pub async fn with_extensions<T, F>(&self, client: ClientId, f: F) -> Result<Option<T>>
+        where T: Send + 'static, F: FnOnce(&mut Extensions) -> T + Send + 'static,
+    {
+        let (sx, rx) = oneshot::channel();
+        let f = Box::new(move |extensions: Option<&mut Extensions>| { sx.send(extensions.map(f)).ok(); });
+        self.send(PoolMessage::Extensions(client, f)).await?;
+        rx.await.map_err(|_| Error::Closed)
+    }
+
+    /// Gets a copy of the extension of type `T` of a Client. `None` if the Client 
+    /// isnt connected or has no such extension.
+    pub async fn extension<T: Clone + Send + Sync + 'static>(&self, client: ClientId) -> Result<Option<T>> {
+        Ok(self.with_extensions(client, |extensions| extensions.get::<T>().cloned()).await?.flatten())
+    }
+
+    /// Attaches an extension to a Client, replacing the one of the same type. 
+    /// Does nothing if the Client isnt connected.
+    pub async fn insert_extension<T: Send + Sync + 'static>(&self, client: ClientId, value: T) -> Result<()> {
+        self.with_extensions(client, |extensions| { extensions.insert(value); }).await.map(|_| ())
+    }
+
+    /// Sends a Response to every Client subscribed to a pattern matching the topic, 
+    /// see `client::Emitter::subscribe`. Clients get it once, even if many of their 
+    /// patterns match.
+    /// 
+    /// Subscriptions are only known after the Collector got their `Event::Subscribe`.
+    #[cfg(feature = "broadcast")]
+    pub async fn publish(&self, topic: &str, res: Res) -> Result<()> {
+        self.send(PoolMessage::Publish(topic.to_string(), res)).await
+    }
+
+    /// Adds a Client to a group, so it receives Responses to `Target::Group`. 
+    /// A Client can be in many groups, and leaves all of them when it disconnects.
+    #[cfg(feature = "broadcast")]
+    pub async fn join_group(&self, client: ClientId, group: GroupId) -> Result<()> {
+        self.send(PoolMessage::Join(client, group)).await
+    }
+
+    /// Removes a Client from a group. Groups without members are forgotten.
+    #[cfg(feature = "broadcast")]
+    pub async fn leave_group(&self, client: ClientId, group: GroupId) -> Result<()> {
+        self.send(PoolMessage::Leave(client, group)).await
+    }
+
+    /// Kicks Clients. Responses emitted before are still sent, then the Client 
+    /// gets a close frame with the reason and the connection is shut down.
+    /// 
+    /// The Client sees `DisconnectEvent::KickedByServer`, and so does the Collector.
+    pub async fn disconnect(&self, target: Target, reason: &str) -> Result<()> {
+        self.send(PoolMessage::Kick(target, reason.to_string())).await
+    }
+
+    async fn send(&self, msg: PoolMessage<Res>) -> Result<()> {
+        // the pool is only gone after a shutdown
+        self.pool.send(msg).await.map_err(|_| Error::Closed)
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 /// The Target of a Response.
 pub enum Target{
     #[cfg(feature = "broadcast")]
@@ -128,12 +394,60 @@ pub enum Target{
     /// 
     /// Equivalent to using .broadcast()
     All,
+    /// Respond to every connected Client except these, e.g. the sender of a chat message.
+    #[cfg(feature = "broadcast")]
+    AllExcept(Vec<ClientId>),
+    /// Respond to each of these Clients.
+    #[cfg(feature = "broadcast")]
+    Many(Vec<ClientId>),
+    /// Respond to every member of a group, see `Emitter::join_group`.
+    #[cfg(feature = "broadcast")]
+    Group(GroupId),
     /// Respond to a specific Client. Origin.into() can be used to create one of these.
-    One(usize),
+    One(ClientId),
+    /// Reply to a specific call of a Client, made with `Client::call`. 
+    /// Origin.into() creates one of these for requests which were sent that way.
+    Reply(ClientId, u64),
+}
+
+/// Names a group of Clients, e.g. a chat room or a lobby. Picked by the application.
+#[cfg(feature = "broadcast")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct GroupId(pub u64);
+
+/// What the Server does when the buffer of a Client is full, because it reads slower 
+/// than Responses are emitted. The Collector gets an `Event::Backpressure` when it starts.
+/// 
+/// Replies to calls and heartbeats are never dropped, replies wait like with `Block`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BackpressurePolicy{
+    /// Wait until the Client catches up. Every other Client waits as well.
+    #[default]
+    Block,
+    /// Drop the new Response.
+    DropNewest,
+    /// Drop the oldest queued Response to make room for the new one.
+    DropOldest,
+    /// Drop the Client with `DisconnectEvent::SlowConsumer`.
+    Disconnect,
+}
+
+/// What a `Server::shutdown` dropped.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ShutdownReport{
+    /// How many Clients were told about the shutdown. `None` if the Responses 
+    /// queued before the shutdown couldnt even be handed out within the grace period.
+    pub clients: Option<usize>,
+    /// Requests which arrived during the shutdown.
+    pub dropped_requests: usize,
+    /// Connections which were still busy after the grace period and got aborted, 
+    /// together with the Responses queued for them.
+    pub aborted: usize,
 }
 
 /// Config for the Server
-pub struct Config{
+#[derive(Debug, Clone)]
+pub struct Config<C = Bincode>{
     /// If no new requests appear within this duration, we drop the client.
     pub timeout: Duration,
     /// The size of the channel buffer per Client Emitter.
@@ -142,11 +456,51 @@ pub struct Config{
     pub collector_buffer: usize,
     /// The size of the channel buffer for the EmitterPool.
     pub pool_buffer: usize,
+    /// Frames larger than this many bytes are skipped and reported as `Event::Oversized`.
+    pub max_frame_size: usize,
+    /// The `Codec` used to encode Responses and decode Requests.
+    pub codec: C,
+    /// How often every Client is pinged. `None` disables sending heartbeats, 
+    /// pings of the Clients are always answered.
+    pub heartbeat_interval: Option<Duration>,
+    /// A Client which doesnt answer a ping within this duration is dropped 
+    /// with `DisconnectEvent::Unresponsive`.
+    pub heartbeat_timeout: Duration,
+    /// When Responses are flushed onto the stream.
+    pub flush_policy: FlushPolicy,
+    /// What happens once the `client_buffer` of a Client is full.
+    pub backpressure: BackpressurePolicy,
+    /// Clients which dont finish the TLS or WebSocket handshake within this 
+    /// duration are dropped before they ever connect.
+    pub handshake_timeout: Duration,
 }
 
-impl Default for Config{
-    fn default() -> Config {
-        Config { timeout: Duration::MAX, client_buffer: 3, collector_buffer: 32, pool_buffer: 32 }
+impl<C> Config<C> {
+    fn settings(&self) -> instance::Settings {
+        instance::Settings{
+            timeout: self.timeout,
+            max_frame_size: self.max_frame_size,
+            heartbeat_interval: self.heartbeat_interval,
+            heartbeat_timeout: self.heartbeat_timeout,
+        }
+    }
+}
+
+impl<C: Default> Default for Config<C>{
+    fn default() -> Config<C> {
+        Config { 
+            timeout: Duration::MAX, 
+            client_buffer: 3, 
+            collector_buffer: 32, 
+            pool_buffer: 32, 
+            max_frame_size: 16 * 1024 * 1024,
+            codec: C::default(),
+            heartbeat_interval: Some(Duration::from_secs(15)),
+            heartbeat_timeout: Duration::from_secs(30),
+            flush_policy: FlushPolicy::Immediate,
+            backpressure: BackpressurePolicy::Block,
+            handshake_timeout: Duration::from_secs(10),
+        }
     }
 }
 
@@ -154,43 +508,74 @@ impl From<Origin> for Target{
     fn from(o: Origin) -> Self {
         match o {
             Origin::Id(i) => Self::One(i),
+            Origin::Call(i, call) => Self::Reply(i, call),
             Origin::OnClient => unreachable!(),
         }
     }
 }
 
-impl<U: Into<usize>> From<U> for Target {
-    fn from(id: U) -> Self {
-        Self::One(id.into())
+impl From<ClientId> for Target {
+    fn from(id: ClientId) -> Self {
+        Self::One(id)
     }
 }
 
-fn accept_loop<Req: Message, Res: Message>(
-    listener: TcpListener,
+fn accept_loop<L: Listener, Req: Message, Res: Message, C: Codec<Req> + Codec<Res>>(
+    mut listener: L,
+    handshake: Handshake,
     sx:   mpsc::Sender<(Event<Req>, Origin)>, 
     pool: mpsc::Sender<PoolMessage<Res>>,
-    timeout: Duration,
-) -> io::Result<()> {
-    let mut id = 0;
+    config: Config<C>,
+    tasks: Tasks,
+) {
+    // a u64 doesnt run out, so ids are never reused
+    let mut next_id = 0;
     
-    tokio::spawn(async move{
+    tasks.clone().spawn(async move{
+        let mut stop = tasks.clone();
         loop{
-            let stream = match listener.accept().await{
-                Ok((stream, _)) => stream,
-                Err(e) => { eprintln!("{}", e); continue },
+            let (stream, mut info) = tokio::select! {
+                accepted = listener.accept() => match accepted {
+                    Ok(accepted) => accepted,
+                    Err(e) => { eprintln!("{}", e); continue },
+                },
+                // dropping the listener stops accepting
+                _ = stop.stopping() => return,
             };
-            let (read, write) = stream.into_split();
+            if sx.is_closed() { return }
+            if let Some(transport) = handshake.transport() {
+                info.transport = transport;
+            }
+            let id = ClientId::new(next_id);
+            next_id += 1;
 
-            instance::Collector::spawn_on_task(read, sx.clone(), id.into(), timeout);
+            // a slow handshake shouldnt block other clients from connecting
+            let (handshake, sx, pool, config) = (handshake.clone(), sx.clone(), pool.clone(), config.clone());
+            let mut conn = tasks.clone();
+            tasks.spawn(async move{
+                let (read, write) = tokio::select! {
+                    halves = tokio::time::timeout(config.handshake_timeout, handshake.split(stream)) => match halves {
+                        Ok(Ok(halves)) => halves,
+                        // it never became a Client, and anyone can make a handshake fail, 
+                        // so this isnt worth an event. Holding on to a slow one would let 
+                        // a peer keep this task alive forever.
+                        Ok(Err(_)) | Err(_) => return,
+                    },
+                    _ = conn.stopping() => return,
+                };
 
-            pool.send(PoolMessage::Connect(write, id)).await.expect("while this owns a sender, the pool wont drop");
+                let (emitter, rx) = instance::queue::channel(config.client_buffer);
+                let (link, report) = instance::Link::new(&emitter);
+                let emitter_task = instance::Emitter::spawn_on_task(write, rx, config.codec.clone(), config.flush_policy, report);
+                if pool.send(PoolMessage::Connect(emitter, id, info.clone())).await.is_err() { return }
 
-            if let Err(_) = sx.send((Event::Connect, id.into())).await{ return };
+                if sx.send((Event::Connect(info), id.into())).await.is_err() { return };
+
+                let collector_task = instance::Collector::spawn_on_task(read, sx, link, id.into(), config.settings(), config.codec, None);
+                conn.watch(emitter_task, collector_task).await;
+            });
     
-            id += 1;
             tokio::task::yield_now().await;
         }
     });
-
-    Ok(())
-}
\ No newline at end of file
+}
@@ -1,76 +1,383 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, ops::ControlFlow};
+#[cfg(feature = "broadcast")]
+use std::{collections::HashSet, error, sync::Arc};
 
-use tokio::{sync::mpsc, net::tcp::OwnedWriteHalf};
+use tokio::sync::{mpsc, oneshot};
 
-use crate::{Message, server::Target, instance};
+#[cfg(feature = "broadcast")]
+use bytes::Bytes;
+
+use crate::{Message, server::{Target, BackpressurePolicy, Extensions}, instance::{Outgoing, CLOSE_KICKED, CLOSE_SHUTDOWN, queue::{self, TrySendError}}, event::{Origin, Event, Backpressure, ConnectionInfo, ClientId}, codec::Codec};
+#[cfg(feature = "broadcast")]
+use crate::{instance, server::{GroupId, topic}};
+
+use super::shutdown::Tasks;
 
 
 ///Lives on a seperate task
 /// 
 /// Handles adding and removing Emitters and propagating Messages.
-pub(crate) struct EmitterPool<Res>{
-    map: HashMap<usize, mpsc::Sender<Res>>,
+pub(crate) struct EmitterPool<Req: Message, Res, C>{
+    map: HashMap<ClientId, Client<Res>>,
+    /// The members of every group. Empty groups are removed.
+    #[cfg(feature = "broadcast")]
+    groups: HashMap<GroupId, HashSet<ClientId>>,
+    /// The subscribers of every topic pattern. Patterns without subscribers are removed.
+    #[cfg(feature = "broadcast")]
+    topics: HashMap<String, HashSet<ClientId>>,
     rx: mpsc::Receiver<PoolMessage<Res>>,
-    client_buffer: usize,
+    /// Broadcasts are encoded once, right here.
+    #[cfg_attr(not(feature = "broadcast"), allow(dead_code))]
+    codec: C,
+    policy: BackpressurePolicy,
+    /// For `Event::Backpressure` and `Event::EncodeError`. Weak, so the Collector still notices once everything else is gone.
+    events: mpsc::WeakSender<(Event<Req>, Origin)>,
+}
+
+/// The Emitter of a Client, and whether it is keeping up.
+struct Client<Res>{
+    sender: queue::Sender<Outgoing<Res>>,
+    info: ConnectionInfo,
+    extensions: Extensions,
+    throttled: bool,
+    dropped: usize,
+    #[cfg(feature = "broadcast")]
+    groups: HashSet<GroupId>,
+    #[cfg(feature = "broadcast")]
+    patterns: HashSet<String>,
 }
 
-impl<Res: Message> EmitterPool<Res> {
+impl<Req: Message, Res: Message, C: Codec<Res>> EmitterPool<Req, Res, C> {
     pub(crate) fn spawn_on_task(
-        pool_buffer: usize,
-        client_buffer: usize,
+        pool_buffer: usize, 
+        codec: C, 
+        policy: BackpressurePolicy, 
+        events: mpsc::Sender<(Event<Req>, Origin)>, 
+        tasks: &Tasks,
     ) -> mpsc::Sender<PoolMessage<Res>> {
         let (sx, rx) = mpsc::channel(pool_buffer);
-        EmitterPool{ rx, map: HashMap::new(), client_buffer }.recv_loop();
+        let events = events.downgrade();
+        EmitterPool{ 
+            rx, 
+            map: HashMap::new(), 
+            #[cfg(feature = "broadcast")]
+            groups: HashMap::new(),
+            #[cfg(feature = "broadcast")]
+            topics: HashMap::new(),
+            codec, 
+            policy, 
+            events,
+        }.recv_loop(tasks);
 
         sx
     }
 
-    fn recv_loop(mut self) {
-        tokio::spawn(async move{
-            loop{
-                let msg = match self.rx.recv().await{
-                    Some(msg) => msg,
-                    //this happens when every emitter has been dropped
-                    None => return,
-                };
-                self.handle_msg(msg).await;
-                tokio::task::yield_now().await;
+    fn recv_loop(self, tasks: &Tasks) {
+        let mut abort = tasks.clone();
+        tasks.spawn(async move{
+            tokio::select! {
+                _ = self.run() => (),
+                // stuck on a Client which doesnt keep up, maybe before it even got the 
+                // shutdown message. The Emitters keep the channel open, so give up here.
+                _ = abort.aborting() => (),
             }
         });
     }
 
-    async fn handle_msg(&mut self, msg: PoolMessage<Res>) {
+    async fn run(mut self) {
+        loop{
+            let msg = match self.rx.recv().await{
+                Some(msg) => msg,
+                //this happens when every emitter has been dropped
+                None => return,
+            };
+            if self.handle_msg(msg).await.is_break() {
+                return
+            }
+            tokio::task::yield_now().await;
+        }
+    }
+
+    /// Everything queued before was already handed to the Emitters. Tells every 
+    /// Client about the shutdown and reports how many there were.
+    fn shutdown(&mut self, clients: oneshot::Sender<usize>) {
+        clients.send(self.map.len()).ok();
+        for (_, client) in self.map.drain() {
+            client.sender.send_last(Outgoing::Close(CLOSE_SHUTDOWN, String::new())).ok();
+        }
+    }
+
+    /// Breaks once the pool is done, after a shutdown.
+    async fn handle_msg(&mut self, msg: PoolMessage<Res>) -> ControlFlow<()> {
         match msg {
-            PoolMessage::Connect(stream, id) => {
-                let (sx, rx) = mpsc::channel(self.client_buffer);
-                instance::Emitter::spawn_on_task(stream, rx);
-                self.map.insert(id, sx);
+            PoolMessage::Connect(sender, id, info) => { 
+                self.map.insert(id, Client{ 
+                    sender, 
+                    info,
+                    extensions: Extensions::new(),
+                    throttled: false, 
+                    dropped: 0, 
+                    #[cfg(feature = "broadcast")]
+                    groups: HashSet::new(),
+                    #[cfg(feature = "broadcast")]
+                    patterns: HashSet::new(),
+                }); 
             },
             PoolMessage::Msg(res, target) => self.send(res, target).await,
-            PoolMessage::Disconnect(id) => { self.map.remove(&id); },
+            PoolMessage::Kick(target, reason) => self.kick(target, reason),
+            PoolMessage::Disconnect(id) => { self.remove(id); },
+            PoolMessage::Clients(clients) => {
+                clients.send(self.map.iter().map(|(id, client)| (*id, client.info.clone())).collect()).ok();
+            },
+            PoolMessage::Count(count) => { count.send(self.map.len()).ok(); },
+            PoolMessage::Extensions(id, f) => f(self.map.get_mut(&id).map(|client| &mut client.extensions)),
+            PoolMessage::Info(id, info) => { info.send(self.map.get(&id).map(|client| client.info.clone())).ok(); },
+            #[cfg(feature = "broadcast")]
+            PoolMessage::Join(id, group) => self.join(id, group),
+            #[cfg(feature = "broadcast")]
+            PoolMessage::Leave(id, group) => self.leave(id, group),
+            #[cfg(feature = "broadcast")]
+            PoolMessage::Subscribe(id, pattern) => self.subscribe(id, pattern),
+            #[cfg(feature = "broadcast")]
+            PoolMessage::Unsubscribe(id, pattern) => self.unsubscribe(id, pattern),
+            #[cfg(feature = "broadcast")]
+            PoolMessage::Publish(topic, res) => self.publish(topic, res).await,
+            PoolMessage::Shutdown(clients) => {
+                self.shutdown(clients);
+                return ControlFlow::Break(())
+            },
+        }
+        ControlFlow::Continue(())
+    }
+
+    /// Queues a close frame behind the pending Responses and forgets the Emitter, 
+    /// which closes the connection once it is done. Never waits for a full queue.
+    fn kick(&mut self, target: Target, reason: String) {
+        for id in self.ids(target) {
+            if let Some(client) = self.remove(id) {
+                client.sender.send_last(Outgoing::Close(CLOSE_KICKED, reason.clone())).ok();
+            }
         }
     }
 
     async fn send(&mut self, res: Res, target: Target) {
         match target {
             #[cfg(feature = "broadcast")]
-            Target::All => {
-                for (_, sender) in self.map.iter() {
-                    let res = res.clone();
-                    sender.send(res).await.expect("instance::collectors shouldnt drop");
+            Target::All | Target::AllExcept(_) | Target::Many(_) | Target::Group(_) => {
+                let ids = self.ids(target);
+                let Some(frame) = self.encode(res, &ids) else { return };
+                for id in ids {
+                    self.push(id, Outgoing::Frame(frame.clone())).await;
                 }
             },
-            Target::One(id) => 
-                if let Some(sender) = self.map.get(&id) {
-                    sender.send(res).await.expect("instance::collectors shouldnt drop");
-                },
+            Target::One(id) => self.push(id, Outgoing::Msg(res)).await,
+            Target::Reply(id, call) => self.push(id, Outgoing::Reply(call, res)).await,
+        }
+    }
+
+    /// Hands a frame to the Emitter of a Client, following the `BackpressurePolicy` if it is full.
+    async fn push(&mut self, id: ClientId, out: Outgoing<Res>) {
+        let Some(client) = self.map.get_mut(&id) else { return };
+
+        let out = match client.sender.try_send(out) {
+            Ok(()) => {
+                if client.throttled {
+                    let dropped = std::mem::take(&mut client.dropped);
+                    client.throttled = false;
+                    self.report(id, Event::Backpressure(Backpressure::Recovered{ dropped }));
+                }
+                return
+            },
+            // the Collector reports the disconnect
+            Err(TrySendError::Closed(_)) => return,
+            Err(TrySendError::Full(out)) => out,
+        };
+
+        let (started, event) = (!client.throttled, match self.policy {
+            BackpressurePolicy::Block => Backpressure::Blocking,
+            _ => Backpressure::Dropping,
+        });
+        client.throttled = true;
+
+        match self.policy {
+            BackpressurePolicy::Block => (),
+            // someone is waiting on a reply, so it waits for room like with `Block`
+            BackpressurePolicy::DropNewest | BackpressurePolicy::DropOldest if !out.droppable() => (),
+            BackpressurePolicy::DropNewest => {
+                client.dropped += 1;
+                if started { self.report(id, Event::Backpressure(event)) }
+                return
+            },
+            BackpressurePolicy::DropOldest => {
+                // replies and heartbeats stay queued, without a Message to replace the new one is dropped
+                client.sender.force_send(out, Outgoing::droppable).ok();
+                client.dropped += 1;
+                if started { self.report(id, Event::Backpressure(event)) }
+                return
+            },
+            BackpressurePolicy::Disconnect => {
+                // the Emitter gives up and the Collector reports `DisconnectEvent::SlowConsumer`
+                if let Some(client) = self.remove(id) {
+                    client.sender.kill();
+                }
+                return
+            },
+        }
+
+        let sender = client.sender.clone();
+        if started { self.report(id, Event::Backpressure(event)) }
+        sender.send(out).await.ok();
+    }
+
+    /// Never waits, a full event buffer would hold up every Client.
+    fn report(&self, id: ClientId, event: Event<Req>) {
+        if let Some(events) = self.events.upgrade() {
+            events.try_send((event, Origin::Id(id))).ok();
+        }
+    }
+
+    /// Every connected Client the Target points to.
+    fn ids(&self, target: Target) -> Vec<ClientId> {
+        match target {
+            #[cfg(feature = "broadcast")]
+            Target::All => self.map.keys().copied().collect(),
+            #[cfg(feature = "broadcast")]
+            Target::AllExcept(except) => {
+                // checked once for every connected Client
+                let except: HashSet<ClientId> = except.into_iter().collect();
+                self.map.keys().copied().filter(|id| !except.contains(id)).collect()
+            },
+            #[cfg(feature = "broadcast")]
+            Target::Many(mut ids) => { ids.sort_unstable(); ids.dedup(); ids },
+            #[cfg(feature = "broadcast")]
+            Target::Group(group) => self.groups.get(&group).map_or(Vec::new(), |members| members.iter().copied().collect()),
+            Target::One(id) | Target::Reply(id, _) => vec![id],
+        }
+    }
+
+    /// Forgets a Client, including its memberships.
+    fn remove(&mut self, id: ClientId) -> Option<Client<Res>> {
+        let client = self.map.remove(&id)?;
+        #[cfg(feature = "broadcast")]
+        for group in client.groups.iter() {
+            self.leave_group(id, *group);
+        }
+        #[cfg(feature = "broadcast")]
+        for pattern in client.patterns.iter() {
+            self.unsubscribe_pattern(id, pattern);
+        }
+        Some(client)
+    }
+
+    /// Clients which arent connected cant join.
+    #[cfg(feature = "broadcast")]
+    fn join(&mut self, id: ClientId, group: GroupId) {
+        if let Some(client) = self.map.get_mut(&id) {
+            client.groups.insert(group);
+            self.groups.entry(group).or_default().insert(id);
+        }
+    }
+
+    #[cfg(feature = "broadcast")]
+    fn leave(&mut self, id: ClientId, group: GroupId) {
+        if let Some(client) = self.map.get_mut(&id) {
+            client.groups.remove(&group);
+            self.leave_group(id, group);
+        }
+    }
+
+    #[cfg(feature = "broadcast")]
+    fn leave_group(&mut self, id: ClientId, group: GroupId) {
+        if let Some(members) = self.groups.get_mut(&group) {
+            members.remove(&id);
+            if members.is_empty() {
+                self.groups.remove(&group);
+            }
+        }
+    }
+
+    /// Clients which arent connected cant subscribe.
+    #[cfg(feature = "broadcast")]
+    fn subscribe(&mut self, id: ClientId, pattern: String) {
+        if let Some(client) = self.map.get_mut(&id) {
+            client.patterns.insert(pattern.clone());
+            self.topics.entry(pattern).or_default().insert(id);
+        }
+    }
+
+    #[cfg(feature = "broadcast")]
+    fn unsubscribe(&mut self, id: ClientId, pattern: String) {
+        if let Some(client) = self.map.get_mut(&id) {
+            client.patterns.remove(&pattern);
+            self.unsubscribe_pattern(id, &pattern);
+        }
+    }
+
+    #[cfg(feature = "broadcast")]
+    fn unsubscribe_pattern(&mut self, id: ClientId, pattern: &str) {
+        if let Some(subscribers) = self.topics.get_mut(pattern) {
+            subscribers.remove(&id);
+            if subscribers.is_empty() {
+                self.topics.remove(pattern);
+            }
+        }
+    }
+
+    #[cfg(feature = "broadcast")]
+    async fn publish(&mut self, topic: String, res: Res) {
+        let ids: HashSet<ClientId> = self.topics.iter()
+            .filter(|(pattern, _)| topic::matches(pattern, &topic))
+            .flat_map(|(_, subscribers)| subscribers.iter().copied())
+            .collect();
+        if ids.is_empty() {
+            return
+        }
+
+        let Some(frame) = self.encode(res, &ids) else { return };
+        for id in ids {
+            self.push(id, Outgoing::Frame(frame.clone())).await;
+        }
+    }
+
+    /// Encodes a Response which goes to many Clients once. Every Emitter shares the frame.
+    /// If that fails, every one of the Clients gets an `Event::EncodeError`.
+    #[cfg(feature = "broadcast")]
+    fn encode<'a>(&self, res: Res, ids: impl IntoIterator<Item = &'a ClientId>) -> Option<Bytes> {
+        match instance::encode(&Outgoing::Msg(res), &self.codec) {
+            Ok(frame) => Some(frame),
+            // the Clients are fine, only this Response is lost
+            Err(err) => {
+                let err: Arc<dyn error::Error + Send + Sync> = err.into();
+                for id in ids {
+                    self.report(*id, Event::EncodeError(err.clone()));
+                }
+                None
+            },
         }
     }
 }
 
-#[derive(Debug)]
+/// Runs on the Extensions of a Client, if it is connected.
+pub(crate) type WithExtensions = Box<dyn FnOnce(Option<&mut Extensions>) + Send>;
+
 pub(crate) enum PoolMessage<Msg>{
-    Connect(OwnedWriteHalf, usize),
+    Connect(queue::Sender<Outgoing<Msg>>, ClientId, ConnectionInfo),
     Msg(Msg, Target),
-    Disconnect(usize),
+    Kick(Target, String),
+    Disconnect(ClientId),
+    Clients(oneshot::Sender<Vec<(ClientId, ConnectionInfo)>>),
+    Count(oneshot::Sender<usize>),
+    Info(ClientId, oneshot::Sender<Option<ConnectionInfo>>),
+    Extensions(ClientId, WithExtensions),
+    #[cfg(feature = "broadcast")]
+    Join(ClientId, GroupId),
+    #[cfg(feature = "broadcast")]
+    Leave(ClientId, GroupId),
+    #[cfg(feature = "broadcast")]
+    Subscribe(ClientId, String),
+    #[cfg(feature = "broadcast")]
+    Unsubscribe(ClientId, String),
+    #[cfg(feature = "broadcast")]
+    Publish(String, Msg),
+    Shutdown(oneshot::Sender<usize>),
 }
\ No newline at end of file
@@ -0,0 +1,99 @@
+use std::{future::Future, sync::{Arc, atomic::{AtomicUsize, Ordering}}};
+
+use tokio::{sync::{mpsc, watch}, task::JoinHandle};
+
+/// How far a Server is in shutting down.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Phase{
+    Running,
+    /// No new Clients are accepted, the connected ones are told to go away.
+    Stopping,
+    /// The grace period is over, everything still running gets aborted.
+    Aborting,
+}
+
+/// Keeps track of every task of a Server. Every task owns a clone.
+#[derive(Debug, Clone)]
+pub(crate) struct Tasks{
+    /// Once every clone is dropped, `Shutdown::done` resolves.
+    alive: mpsc::Sender<()>,
+    phase: watch::Receiver<Phase>,
+    aborted: Arc<AtomicUsize>,
+}
+
+/// Drives the shutdown of the `Tasks`.
+#[derive(Debug)]
+pub(crate) struct Shutdown{
+    done: mpsc::Receiver<()>,
+    phase: watch::Sender<Phase>,
+    aborted: Arc<AtomicUsize>,
+}
+
+pub(crate) fn tasks() -> (Tasks, Shutdown) {
+    let (alive, done) = mpsc::channel(1);
+    let (phase_sx, phase) = watch::channel(Phase::Running);
+    let aborted = Arc::new(AtomicUsize::new(0));
+
+    (Tasks{alive, phase, aborted: aborted.clone()}, Shutdown{done, phase: phase_sx, aborted})
+}
+
+impl Tasks {
+    pub fn spawn<F: Future<Output = ()> + Send + 'static>(&self, task: F) {
+        let alive = self.alive.clone();
+        tokio::spawn(async move{
+            task.await;
+            drop(alive);
+        });
+    }
+
+    /// Resolves once the Server starts shutting down.
+    pub async fn stopping(&mut self) {
+        self.reached(Phase::Stopping).await
+    }
+
+    /// Resolves once the grace period of a shutdown is over.
+    pub async fn aborting(&mut self) {
+        self.reached(Phase::Aborting).await
+    }
+
+    /// Waits until both tasks of a connection are done, or aborts them once the grace period is over.
+    pub async fn watch(mut self, emitter: JoinHandle<()>, collector: JoinHandle<()>) {
+        let handles = (emitter.abort_handle(), collector.abort_handle());
+
+        tokio::select! {
+            _ = async { emitter.await.ok(); collector.await.ok(); } => (),
+            _ = self.reached(Phase::Aborting) => {
+                handles.0.abort();
+                handles.1.abort();
+                self.aborted.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    async fn reached(&mut self, phase: Phase) {
+        // a Server which was dropped without a shutdown keeps its tasks running, like before
+        if self.phase.wait_for(|p| *p >= phase).await.is_err() {
+            std::future::pending().await
+        }
+    }
+}
+
+impl Shutdown {
+    pub fn stop(&self) {
+        self.phase.send_replace(Phase::Stopping);
+    }
+
+    pub fn abort(&self) {
+        self.phase.send_replace(Phase::Aborting);
+    }
+
+    /// Resolves once every task is done.
+    pub async fn done(&mut self) {
+        self.done.recv().await;
+    }
+
+    /// How many connections had to be aborted.
+    pub fn aborted(&self) -> usize {
+        self.aborted.load(Ordering::Relaxed)
+    }
+}
@@ -0,0 +1,15 @@
+/// Whether a topic matches a subscribed pattern. Both are split into segments by `.`, 
+/// and a `*` segment of the pattern matches any one segment of the topic.
+pub(crate) fn matches(pattern: &str, topic: &str) -> bool {
+    let mut pattern = pattern.split('.');
+    let mut topic = topic.split('.');
+
+    loop{
+        match (pattern.next(), topic.next()) {
+            (None, None) => return true,
+            (Some("*"), Some(_)) => continue,
+            (Some(p), Some(t)) if p == t => continue,
+            _ => return false,
+        }
+    }
+}
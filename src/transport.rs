@@ -0,0 +1,75 @@
+//! Traits for running connections over custom transports.
+//! 
+//! Kumoko ships with TCP and Unix domain socket support, but any stream 
+//! implementing `AsyncRead + AsyncWrite` can carry a connection. Use 
+//! `Client::from_stream` on the client side and `Server::from_listener` 
+//! with your own `Listener` on the server side.
+
+use std::time::Duration;
+#[cfg(feature = "server")]
+use std::{future::Future, io};
+
+use tokio::io::{AsyncRead, AsyncWrite};
+#[cfg(feature = "server")]
+use tokio::net::{TcpListener, TcpStream};
+#[cfg(all(feature = "server", unix))]
+use tokio::net::{UnixListener, UnixStream};
+
+#[cfg(feature = "server")]
+use crate::event::{ConnectionInfo, TransportKind};
+
+/// Any stream a connection can run on, e.g. a `TcpStream`, a 
+/// `tokio::io::DuplexStream` or a TLS stream.
+pub trait Stream:               AsyncRead + AsyncWrite + Send + Unpin + 'static{}
+impl<T> Stream for T where T:   AsyncRead + AsyncWrite + Send + Unpin + 'static{}
+
+/// When an Emitter flushes the frames it wrote. Frames which are already 
+/// queued are always coalesced into a single write.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FlushPolicy{
+    /// Write and flush as soon as a frame is queued. The lowest latency.
+    #[default]
+    Immediate,
+    /// Wait up to `max_delay` after the first frame for more frames, then write 
+    /// and flush them together. Fewer syscalls and, on WebSockets, fewer messages.
+    Batched{ max_delay: Duration },
+}
+
+/// Accepts new connections for a `Server`. Enable the server feature to use it.
+/// 
+/// `accept` is polled in a loop on its own task, so it should return as soon 
+/// as a stream is available.
+#[cfg(feature = "server")]
+pub trait Listener: Send + 'static {
+    /// The stream of an accepted connection.
+    type Stream: Stream;
+
+    /// Waits for the next connection.
+    fn accept(&mut self) -> impl Future<Output = io::Result<(Self::Stream, ConnectionInfo)>> + Send;
+}
+
+#[cfg(feature = "server")]
+impl Listener for TcpListener {
+    type Stream = TcpStream;
+
+    async fn accept(&mut self) -> io::Result<(TcpStream, ConnectionInfo)> {
+        let (stream, peer_addr) = TcpListener::accept(self).await?;
+        let info = ConnectionInfo{ 
+            peer_addr: Some(peer_addr), 
+            local_addr: stream.local_addr().ok(), 
+            ..ConnectionInfo::new(TransportKind::Tcp) 
+        };
+        Ok((stream, info))
+    }
+}
+
+#[cfg(all(feature = "server", unix))]
+impl Listener for UnixListener {
+    type Stream = UnixStream;
+
+    async fn accept(&mut self) -> io::Result<(UnixStream, ConnectionInfo)> {
+        let (stream, _) = UnixListener::accept(self).await?;
+        let credentials = stream.peer_cred().ok().map(Into::into);
+        Ok((stream, ConnectionInfo{ credentials, ..ConnectionInfo::new(TransportKind::Unix) }))
+    }
+}
@@ -0,0 +1,200 @@
+use std::time::Duration;
+
+use kumoko::{event::{Event, Origin, ClientId, Backpressure, DisconnectEvent}, server::{Server, Config, BackpressurePolicy}};
+use tokio::{io::AsyncWriteExt, net::{TcpSocket, TcpStream}, time::timeout};
+
+mod common;
+
+const BIG: usize = 1024 * 1024;
+
+async fn slow_server(ip: &'static str, backpressure: BackpressurePolicy) -> (Server<i32, Vec<u8>>, TcpStream, ClientId) {
+    let config = Config{ client_buffer: 1, heartbeat_interval: None, backpressure, ..Default::default() };
+    let mut server = Server::<i32, Vec<u8>>::bind_with_config(ip, config).await.unwrap();
+    // the tests read from it once the Emitter may move on again
+    let stream = common::connect_stuck(ip).await;
+    let id = match server.get_event().await.unwrap() {
+        (Event::Connect(_), Origin::Id(id)) => id,
+        e => panic!("{:?}", e),
+    };
+
+    (server, stream, id)
+}
+
+/// Emits big Responses until the Server reports an event. Returns it and how many Responses were emitted.
+async fn flood(server: &mut Server<i32, Vec<u8>>, id: ClientId) -> (Event<i32>, usize) {
+    for i in 0..64u8 {
+        server.emit_response(vec![i; BIG], id.into()).await.unwrap();
+        if let Ok(event) = timeout(Duration::from_millis(20), server.get_event()).await {
+            return (event.unwrap().0, i as usize + 1)
+        }
+    }
+    panic!("the Server never noticed the slow Client")
+}
+
+/// Reads Message frames until one ends with `last`, returning the last byte of each.
+async fn read_until(stream: &mut TcpStream, last: u8) -> Vec<u8> {
+    let mut seen = Vec::new();
+    loop{
+        let (kind, payload) = common::read_frame(stream).await.unwrap();
+        if kind != 0 { continue }
+        let fill = *payload.last().unwrap();
+        seen.push(fill);
+        if fill == last { return seen }
+    }
+}
+
+#[tokio::test]
+async fn drop_newest() {
+    const IP: &str = "[::1]:50083";
+    let (mut server, mut stream, id) = slow_server(IP, BackpressurePolicy::DropNewest).await;
+
+    assert!(matches!(flood(&mut server, id).await.0, Event::Backpressure(Backpressure::Dropping)));
+
+    let reader = tokio::spawn(async move{ read_until(&mut stream, 255).await });
+    let dropped = loop{
+        server.emit_response(vec![255; 16], id.into()).await.unwrap();
+        match timeout(Duration::from_millis(20), server.get_event()).await {
+            Ok(event) => match event.unwrap().0 {
+                Event::Backpressure(Backpressure::Recovered{ dropped }) => break dropped,
+                e => panic!("{:?}", e),
+            },
+            Err(_) => continue,
+        }
+    };
+
+    assert!(dropped > 0);
+    let seen = reader.await.unwrap();
+    // the Responses which made it arrive in order
+    assert!(seen.windows(2).all(|w| w[0] < w[1]));
+}
+
+#[tokio::test]
+async fn drop_oldest() {
+    const IP: &str = "[::1]:50084";
+    let (mut server, mut stream, id) = slow_server(IP, BackpressurePolicy::DropOldest).await;
+
+    let (event, sent) = flood(&mut server, id).await;
+    assert!(matches!(event, Event::Backpressure(Backpressure::Dropping)));
+    let newest = 250;
+    server.emit_response(vec![newest; BIG], id.into()).await.unwrap();
+
+    let seen = timeout(Duration::from_secs(5), read_until(&mut stream, newest)).await.unwrap();
+    assert!(seen.windows(2).all(|w| w[0] < w[1]));
+    // the Responses stuck in the queue were replaced by the newest one
+    assert!(seen.len() < sent + 1);
+}
+
+#[tokio::test]
+async fn disconnect() {
+    const IP: &str = "[::1]:50085";
+    let (mut server, _stream, id) = slow_server(IP, BackpressurePolicy::Disconnect).await;
+
+    let (event, _) = flood(&mut server, id).await;
+    assert!(matches!(event, Event::Disconnect(DisconnectEvent::SlowConsumer)), "{:?}", event);
+}
+
+#[tokio::test]
+async fn block() {
+    const IP: &str = "[::1]:50086";
+    let (mut server, mut stream, id) = slow_server(IP, BackpressurePolicy::Block).await;
+
+    let (event, sent) = flood(&mut server, id).await;
+    assert!(matches!(event, Event::Backpressure(Backpressure::Blocking)));
+
+    let reader = tokio::spawn(async move{ read_until(&mut stream, 255).await });
+    server.emit_response(vec![255; 16], id.into()).await.unwrap();
+
+    // nothing is dropped, everything arrives once the Client reads again
+    let seen = timeout(Duration::from_secs(5), reader).await.unwrap().unwrap();
+    assert_eq!(seen.len(), sent + 1);
+}
+
+#[tokio::test]
+async fn heartbeats_survive_drop_oldest() {
+    const IP: &str = "[::1]:50099";
+    let config = Config{ 
+        client_buffer: 1, 
+        heartbeat_interval: Some(Duration::from_millis(10)), 
+        heartbeat_timeout: Duration::from_secs(2),
+        backpressure: BackpressurePolicy::DropOldest, 
+        ..Default::default()
+    };
+    let server = Server::<i32, Vec<u8>>::bind_with_config(IP, config).await.unwrap();
+    // small socket buffers, so a ping doesnt queue up behind megabytes in the kernel
+    let socket = TcpSocket::new_v6().unwrap();
+    socket.set_recv_buffer_size(16 * 1024).unwrap();
+    let (mut read, mut write) = socket.connect(IP.parse().unwrap()).await.unwrap().into_split();
+
+    // reads slowly, but answers every ping
+    tokio::spawn(async move{
+        loop{
+            let Ok((kind, payload)) = common::read_frame(&mut read).await else { return };
+            match kind {
+                3 => {
+                    let mut pong = vec![0, 0, 0, 8, 4];
+                    pong.extend_from_slice(&payload);
+                    if write.write_all(&pong).await.is_err() { return }
+                },
+                _ => tokio::time::sleep(Duration::from_millis(1)).await,
+            }
+        }
+    });
+
+    let (mut collector, emitter) = server.into_split();
+    let id = match collector.get_event().await.unwrap() {
+        (Event::Connect(_), Origin::Id(id)) => id,
+        e => panic!("{:?}", e),
+    };
+    tokio::spawn(async move{
+        while emitter.emit_response(vec![1; 16 * 1024], id.into()).await.is_ok() {}
+    });
+
+    let (mut rtts, mut dropping) = (0, false);
+    let flooding = tokio::time::sleep(Duration::from_secs(1));
+    tokio::pin!(flooding);
+    loop{
+        tokio::select! {
+            _ = &mut flooding => break,
+            event = collector.get_event() => match event.unwrap().0 {
+                Event::Rtt(_) => rtts += 1,
+                Event::Backpressure(Backpressure::Dropping) => dropping = true,
+                Event::Disconnect(reason) => panic!("{:?}", reason),
+                _ => (),
+            },
+        }
+    }
+    assert!(dropping);
+    assert!(rtts > 0);
+}
+
+#[tokio::test]
+async fn replies_survive_drop_oldest() {
+    const IP: &str = "[::1]:50100";
+    let (mut server, mut stream, id) = slow_server(IP, BackpressurePolicy::DropOldest).await;
+
+    let (event, _) = flood(&mut server, id).await;
+    assert!(matches!(event, Event::Backpressure(Backpressure::Dropping)));
+
+    // a call with id 7, while the queue is full
+    let req = bincode::encode_to_vec(1i32, bincode::config::standard()).unwrap();
+    let mut call = ((8 + req.len()) as u32).to_be_bytes().to_vec();
+    call.push(1);
+    call.extend_from_slice(&7u64.to_be_bytes());
+    call.extend_from_slice(&req);
+    stream.write_all(&call).await.unwrap();
+
+    let (_, origin) = server.get_request().await.unwrap();
+    server.emit_response(vec![200; 16], origin.into()).await.unwrap();
+    // these would push the reply out of the queue
+    for _ in 0..4 {
+        server.emit_response(vec![201; 16], id.into()).await.unwrap();
+    }
+
+    let reply = timeout(Duration::from_secs(5), async {
+        loop{
+            let (kind, payload) = common::read_frame(&mut stream).await.unwrap();
+            if kind == 2 { return payload }
+        }
+    }).await.unwrap();
+    assert_eq!(reply[..8], 7u64.to_be_bytes());
+}
@@ -0,0 +1,62 @@
+use std::time::Duration;
+
+use kumoko::{client::{Client, Config}, transport::FlushPolicy};
+use tokio::io::{AsyncReadExt, DuplexStream};
+
+mod common;
+
+async fn read_message(stream: &mut DuplexStream) -> Vec<u8> {
+    let (kind, payload) = common::read_frame(stream).await.unwrap();
+    assert_eq!(kind, 0);
+    bincode::decode_from_slice(&payload, bincode::config::standard()).unwrap().0
+}
+
+#[tokio::test]
+async fn partial_writes() {
+    // every write of the Emitter gets cut short
+    let (stream, mut server) = tokio::io::duplex(64);
+    let client = Client::<Vec<u8>, i32>::from_stream(stream);
+
+    tokio::spawn(async move{
+        for i in 0..5 {
+            client.emit_request(vec![i; 100_000]).await.unwrap();
+        }
+    });
+    for i in 0..5 {
+        assert_eq!(read_message(&mut server).await, vec![i; 100_000]);
+    }
+}
+
+#[tokio::test]
+async fn batched() {
+    let (stream, mut server) = tokio::io::duplex(4096);
+    let config = Config{ flush_policy: FlushPolicy::Batched{ max_delay: Duration::from_millis(100) }, ..Default::default() };
+    let client = Client::<Vec<u8>, i32>::from_stream_with_config(stream, config);
+
+    client.emit_request(vec![1]).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    client.emit_request(vec![2]).await.unwrap();
+    client.emit_request(vec![3]).await.unwrap();
+
+    // all three frames arrive with a single write
+    let mut buf = [0; 4096];
+    let read = server.read(&mut buf).await.unwrap();
+    assert_eq!(read, 3 * 7);
+}
+
+#[tokio::test]
+async fn burst() {
+    // tcp supports vectored writes
+    const IP: &str = "[::1]:50081";
+    let mut server = kumoko::server::Server::<Vec<u8>, i32>::bind(IP).await.unwrap();
+    let client = Client::<Vec<u8>, i32>::connect(IP).await.unwrap();
+
+    tokio::spawn(async move{
+        for i in 0..200u32 {
+            client.emit_request(vec![i as u8; i as usize * 100]).await.unwrap();
+        }
+    });
+    for i in 0..200u32 {
+        assert_eq!(server.get_request().await.unwrap().0, vec![i as u8; i as usize * 100]);
+    }
+}
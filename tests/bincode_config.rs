@@ -0,0 +1,34 @@
+use kumoko::{client::{Client, self}, server::{Server, self}, codec::{Bincode, bincode::config}, event::Event::*};
+use tokio::{io::AsyncReadExt, net::TcpListener};
+
+#[tokio::test]
+async fn fixint_big_endian() {
+    const IP: &str = "[::1]:50063";
+    let listener = TcpListener::bind(IP).await.unwrap();
+
+    let codec = Bincode::with_config(config::standard().with_fixed_int_encoding().with_big_endian());
+    let config = client::Config{ codec, ..Default::default() };
+    let client = Client::<u32, u32, _>::connect_with_config(IP, config).await.unwrap();
+    client.emit_request(0x01020304).await.unwrap();
+
+    let (mut stream, _) = listener.accept().await.unwrap();
+    let mut frame = [0; 9];
+    stream.read_exact(&mut frame).await.unwrap();
+    assert_eq!(frame, [0, 0, 0, 4, 0, 1, 2, 3, 4]);
+}
+
+#[tokio::test]
+async fn limit() {
+    const IP: &str = "[::1]:50064";
+    let codec = Bincode::with_config(config::standard().with_limit::<16>());
+    let config = server::Config{ codec, ..Default::default() };
+    let mut server = Server::<Vec<u8>, Vec<u8>, _>::bind_with_config(IP, config).await.unwrap();
+    let client = Client::<Vec<u8>, Vec<u8>>::connect(IP).await.unwrap();
+
+    client.emit_request(vec![0; 100]).await.unwrap();
+    client.emit_request(vec![1, 2, 3]).await.unwrap();
+
+    assert!(matches!(server.get_event().await.unwrap().0, Connect(_)));
+    assert!(matches!(server.get_event().await.unwrap().0, IllegalData(_)));
+    assert_eq!(server.get_request().await.unwrap().0, vec![1, 2, 3]);
+}
@@ -0,0 +1,132 @@
+use kumoko::{client::{self, Client}, server::{self, Server, Target, GroupId}, event::{Event, Origin}, Encode, Decode};
+
+mod common;
+use common::Picky;
+
+/// Doesnt implement Clone, which broadcasting used to require.
+#[derive(Debug, PartialEq, Encode, Decode)]
+struct News(String);
+
+#[tokio::test]
+async fn broadcast() {
+    const IP: &str = "[::1]:50082";
+    let mut server = Server::<i32, News>::bind(IP).await.unwrap();
+
+    let mut clients = Vec::new();
+    for _ in 0..3 {
+        clients.push(Client::<i32, News>::connect(IP).await.unwrap());
+        assert!(matches!(server.get_event().await.unwrap().0, Event::Connect(_)));
+    }
+
+    server.broadcast(News("Ferris is a crab".to_string())).await.unwrap();
+    for client in clients.iter_mut() {
+        assert_eq!(client.get_response().await, Some(News("Ferris is a crab".to_string())));
+    }
+}
+
+#[tokio::test]
+async fn groups() {
+    const IP: &str = "[::1]:50087";
+    const LOBBY: GroupId = GroupId(7);
+    let mut server = Server::<i32, News>::bind(IP).await.unwrap();
+
+    let mut clients = Vec::new();
+    let mut ids = Vec::new();
+    for _ in 0..3 {
+        clients.push(Client::<i32, News>::connect(IP).await.unwrap());
+        match server.get_event().await.unwrap() {
+            (Event::Connect(_), Origin::Id(id)) => ids.push(id),
+            e => panic!("{:?}", e),
+        }
+    }
+
+    server.join_group(ids[0], LOBBY).await.unwrap();
+    server.join_group(ids[1], LOBBY).await.unwrap();
+    server.emit_response(News("lobby".to_string()), Target::Group(LOBBY)).await.unwrap();
+    server.broadcast(News("everyone".to_string())).await.unwrap();
+
+    for client in clients.iter_mut().take(2) {
+        assert_eq!(client.get_response().await, Some(News("lobby".to_string())));
+        assert_eq!(client.get_response().await, Some(News("everyone".to_string())));
+    }
+    assert_eq!(clients[2].get_response().await, Some(News("everyone".to_string())));
+
+    // disconnecting leaves the group, so does leave_group
+    drop(clients.remove(1));
+    assert!(matches!(server.get_event().await.unwrap().0, Event::Disconnect(_)));
+    server.leave_group(ids[0], LOBBY).await.unwrap();
+    server.emit_response(News("lobby".to_string()), Target::Group(LOBBY)).await.unwrap();
+    server.broadcast(News("everyone".to_string())).await.unwrap();
+
+    for client in clients.iter_mut() {
+        assert_eq!(client.get_response().await, Some(News("everyone".to_string())));
+    }
+}
+
+#[tokio::test]
+async fn many_and_except() {
+    const IP: &str = "[::1]:50088";
+    let mut server = Server::<i32, News>::bind(IP).await.unwrap();
+
+    let mut clients = Vec::new();
+    let mut ids = Vec::new();
+    for _ in 0..3 {
+        clients.push(Client::<i32, News>::connect(IP).await.unwrap());
+        match server.get_event().await.unwrap() {
+            (Event::Connect(_), Origin::Id(id)) => ids.push(id),
+            e => panic!("{:?}", e),
+        }
+    }
+
+    // duplicates and Clients which arent connected anymore are ignored
+    drop(Client::<i32, News>::connect(IP).await.unwrap());
+    let gone = match server.get_event().await.unwrap() {
+        (Event::Connect(_), Origin::Id(id)) => id,
+        e => panic!("{:?}", e),
+    };
+    assert!(matches!(server.get_event().await.unwrap().0, Event::Disconnect(_)));
+
+    server.emit_response(News("many".to_string()), Target::Many(vec![ids[0], ids[2], ids[2], gone])).await.unwrap();
+    server.emit_response(News("except".to_string()), Target::AllExcept(vec![ids[0]])).await.unwrap();
+    server.broadcast(News("everyone".to_string())).await.unwrap();
+
+    let expected = [vec!["many", "everyone"], vec!["except", "everyone"], vec!["many", "except", "everyone"]];
+    for (client, expected) in clients.iter_mut().zip(expected) {
+        for news in expected {
+            assert_eq!(client.get_response().await, Some(News(news.to_string())));
+        }
+    }
+}
+
+#[tokio::test]
+async fn unencodable() {
+    const IP: &str = "[::1]:50104";
+    let mut server = Server::<i32, i32, Picky>::bind_with_config(IP, server::Config::default()).await.unwrap();
+
+    let mut clients = Vec::new();
+    let mut ids = Vec::new();
+    for _ in 0..2 {
+        clients.push(Client::<i32, i32, Picky>::connect_with_config(IP, client::Config::default()).await.unwrap());
+        match server.get_event().await.unwrap() {
+            (Event::Connect(_), Origin::Id(id)) => ids.push(id),
+            e => panic!("{:?}", e),
+        }
+    }
+
+    server.broadcast(-1).await.unwrap();
+    let mut failed = Vec::new();
+    for _ in 0..2 {
+        match server.get_event().await.unwrap() {
+            (Event::EncodeError(_), Origin::Id(id)) => failed.push(id),
+            e => panic!("{:?}", e),
+        }
+    }
+    failed.sort();
+    assert_eq!(failed, ids);
+
+    // only that Response was lost
+    server.broadcast(1).await.unwrap();
+    for client in clients.iter_mut() {
+        assert_eq!(client.get_response().await, Some(1));
+    }
+}
@@ -0,0 +1,50 @@
+use kumoko::{client::{Client, self}, server::{Server, self}, codec::Codec};
+use serde::{Serialize, Deserialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Login{
+    username: String,
+    password: String,
+}
+
+async fn roundtrip<C: Codec<Login> + Default>(ip: &'static str) {
+    let config = server::Config::<C>::default();
+    let mut server = Server::<Login, Login, C>::bind_with_config(ip, config).await.unwrap();
+    let config = client::Config::<C>::default();
+    let mut client = Client::<Login, Login, C>::connect_with_config(ip, config).await.unwrap();
+
+    let login = Login{ username: "Ferris".to_string(), password: "[rab$Rav3".to_string() };
+    client.emit_request(login.clone()).await.unwrap();
+
+    let (req, origin) = server.get_request().await.unwrap();
+    assert_eq!(req, login);
+    server.emit_response(req, origin.into()).await.unwrap();
+
+    assert_eq!(client.get_response().await.unwrap(), login);
+}
+
+#[tokio::test]
+async fn json() {
+    roundtrip::<kumoko::codec::Json>("[::1]:50060").await;
+}
+
+#[tokio::test]
+async fn postcard() {
+    roundtrip::<kumoko::codec::Postcard>("[::1]:50061").await;
+}
+
+#[tokio::test]
+async fn msgpack() {
+    roundtrip::<kumoko::codec::MsgPack>("[::1]:50062").await;
+}
+
+#[tokio::test]
+async fn json_is_readable() {
+    use kumoko::codec::Json;
+    let login = Login{ username: "Ferris".to_string(), password: "crabrave".to_string() };
+
+    let mut buf = Vec::new();
+    Json.encode(&login, &mut buf).unwrap();
+    assert_eq!(buf, br#"{"username":"Ferris","password":"crabrave"}"#);
+    assert_eq!(Codec::<Login>::decode(&Json, &buf).unwrap(), login);
+}
@@ -0,0 +1,54 @@
+//! Helpers shared by the integration tests, which speak the wire format by hand.
+#![allow(dead_code)]
+
+use std::io;
+
+use kumoko::codec::{self, Codec, Bincode};
+use tokio::{io::{AsyncRead, AsyncReadExt}, net::{TcpListener, TcpStream}};
+
+/// A frame of any kind, see the wire format in the README.
+pub fn frame(kind: u8, payload: &[u8]) -> Vec<u8> {
+    let mut buf = (payload.len() as u32).to_be_bytes().to_vec();
+    buf.push(kind);
+    buf.extend_from_slice(payload);
+    buf
+}
+
+/// Reads the next frame. Returns its kind and payload.
+pub async fn read_frame<R: AsyncRead + Unpin>(stream: &mut R) -> io::Result<(u8, Vec<u8>)> {
+    let mut header = [0; 5];
+    stream.read_exact(&mut header).await?;
+    let len = u32::from_be_bytes(header[..4].try_into().unwrap()) as usize;
+    let mut payload = vec![0; len];
+    stream.read_exact(&mut payload).await?;
+    Ok((header[4], payload))
+}
+
+/// Connects without ever reading, so the Emitter of the Server gets stuck 
+/// once the socket buffers are full.
+pub async fn connect_stuck(ip: &str) -> TcpStream {
+    TcpStream::connect(ip).await.unwrap()
+}
+
+/// Accepts a connection without ever reading, so the Emitter of the Client 
+/// gets stuck once the socket buffers are full.
+pub async fn accept_stuck(listener: &TcpListener) -> TcpStream {
+    listener.accept().await.unwrap().0
+}
+
+/// Refuses to encode negative numbers.
+#[derive(Clone, Default)]
+pub struct Picky(Bincode);
+
+impl Codec<i32> for Picky {
+    fn encode(&self, msg: &i32, buf: &mut Vec<u8>) -> Result<(), codec::Error> {
+        match *msg < 0 {
+            true => Err("negative".into()),
+            false => self.0.encode(msg, buf),
+        }
+    }
+
+    fn decode(&self, payload: &[u8]) -> Result<i32, codec::Error> {
+        self.0.decode(payload)
+    }
+}
@@ -0,0 +1,138 @@
+use std::time::Duration;
+
+use kumoko::{client::Client, server::{self, Server, BackpressurePolicy}, event::{Event, DisconnectEvent, Origin, Backpressure}};
+use tokio::{io::AsyncWriteExt, net::{TcpListener, TcpStream}};
+
+mod common;
+use common::frame;
+
+fn close(code: u16, reason: &str) -> Vec<u8> {
+    let mut payload = code.to_be_bytes().to_vec();
+    payload.extend_from_slice(reason.as_bytes());
+    frame(5, &payload)
+}
+
+async fn client_sees(ip: &str, bytes: Vec<u8>) -> DisconnectEvent {
+    let listener = TcpListener::bind(ip).await.unwrap();
+    let mut client = Client::<i32, i32>::connect(ip).await.unwrap();
+    let (mut stream, _) = listener.accept().await.unwrap();
+    stream.write_all(&bytes).await.unwrap();
+
+    let reason = match client.get_event().await {
+        Some(Event::Disconnect(reason)) => reason,
+        e => panic!("expected a disconnect, got {:?}", e),
+    };
+    assert!(client.get_event().await.is_none());
+    reason
+}
+
+#[tokio::test]
+async fn close_frames() {
+    let reason = client_sees("[::1]:50070", close(1, "spamming")).await;
+    assert!(matches!(reason, DisconnectEvent::KickedByServer{ reason } if reason == "spamming"));
+
+    let reason = client_sees("[::1]:50071", close(2, "")).await;
+    assert!(matches!(reason, DisconnectEvent::ServerShutdown));
+
+    let reason = client_sees("[::1]:50072", close(4000, "custom")).await;
+    assert!(matches!(reason, DisconnectEvent::PeerClosed{ code: 4000, reason } if reason == "custom"));
+
+    let reason = client_sees("[::1]:50073", frame(1, &[])).await;
+    assert!(matches!(reason, DisconnectEvent::ProtocolViolation(_)));
+}
+
+#[tokio::test]
+async fn timeout() {
+    const IP: &str = "[::1]:50074";
+    let config = server::Config{ 
+        timeout: Duration::from_millis(50), 
+        heartbeat_interval: None, 
+        ..Default::default() 
+    };
+    let mut server = Server::<i32, i32>::bind_with_config(IP, config).await.unwrap();
+    let _stream = TcpStream::connect(IP).await.unwrap();
+
+    assert!(matches!(server.get_event().await.unwrap().0, Event::Connect(_)));
+    assert!(matches!(server.get_event().await.unwrap().0, Event::Disconnect(DisconnectEvent::Timeout)));
+}
+
+#[tokio::test]
+async fn peer_closed() {
+    const IP: &str = "[::1]:50075";
+    let mut server = Server::<i32, i32>::bind(IP).await.unwrap();
+    let mut stream = TcpStream::connect(IP).await.unwrap();
+    stream.write_all(&close(1, "the server cant be kicked")).await.unwrap();
+
+    assert!(matches!(server.get_event().await.unwrap().0, Event::Connect(_)));
+    assert!(matches!(server.get_event().await.unwrap().0, Event::Disconnect(DisconnectEvent::PeerClosed{ code: 1, .. })));
+
+    let client = Client::<i32, i32>::connect(IP).await.unwrap();
+    drop(client);
+    assert!(matches!(server.get_event().await.unwrap().0, Event::Connect(_)));
+    assert!(matches!(server.get_event().await.unwrap().0, Event::Disconnect(DisconnectEvent::Clean)));
+}
+
+#[tokio::test]
+async fn kick() {
+    const IP: &str = "[::1]:50076";
+    let mut server = Server::<i32, i32>::bind(IP).await.unwrap();
+    let mut client = Client::<i32, i32>::connect(IP).await.unwrap();
+    let mut other = Client::<i32, i32>::connect(IP).await.unwrap();
+
+    client.emit_request(1).await.unwrap();
+    let (_, origin) = server.get_request().await.unwrap();
+
+    // the pending response still arrives
+    server.emit_response(10, origin.into()).await.unwrap();
+    server.disconnect(origin.into(), "spamming").await.unwrap();
+
+    assert_eq!(client.get_response().await, Some(10));
+    assert!(matches!(client.get_event().await, Some(Event::Disconnect(DisconnectEvent::KickedByServer{ reason })) if reason == "spamming"));
+    assert!(client.get_event().await.is_none());
+
+    loop {
+        match server.get_event().await.unwrap() {
+            (Event::Disconnect(DisconnectEvent::KickedByServer{ .. }), o) => { assert!(matches!((o, origin), (Origin::Id(a), Origin::Id(b)) if a == b)); break },
+            (Event::Disconnect(_), _) => panic!("expected a kick"),
+            _ => continue,
+        }
+    }
+
+    // other Clients stay connected
+    other.emit_request(2).await.unwrap();
+    let (req, o) = server.get_request().await.unwrap();
+    assert_eq!(req, 2);
+    server.emit_response(20, o.into()).await.unwrap();
+    assert_eq!(other.get_response().await, Some(20));
+}
+
+#[tokio::test]
+async fn kick_stuck() {
+    const IP: &str = "[::1]:50105";
+    let config = server::Config{ 
+        client_buffer: 1, 
+        heartbeat_interval: None, 
+        backpressure: BackpressurePolicy::DropNewest, 
+        ..Default::default() 
+    };
+    let mut server = Server::<i32, Vec<u8>>::bind_with_config(IP, config).await.unwrap();
+    let _stream = common::connect_stuck(IP).await;
+    let Ok((Event::Connect(_), Origin::Id(stuck))) = server.get_event().await else { panic!("expected a connect") };
+    let mut other = Client::<i32, Vec<u8>>::connect(IP).await.unwrap();
+    let Ok((Event::Connect(_), Origin::Id(id))) = server.get_event().await else { panic!("expected a connect") };
+
+    // fills its queue for good
+    loop{
+        server.emit_response(vec![1; 1024 * 1024], stuck.into()).await.unwrap();
+        if let Ok(event) = tokio::time::timeout(Duration::from_millis(20), server.get_event()).await {
+            assert!(matches!(event.unwrap().0, Event::Backpressure(Backpressure::Dropping)));
+            break
+        }
+    }
+
+    // the close frame doesnt wait for room in the queue, so neither does anyone else
+    server.disconnect(stuck.into(), "too slow").await.unwrap();
+    server.emit_response(vec![2], id.into()).await.unwrap();
+    let res = tokio::time::timeout(Duration::from_secs(1), other.get_response()).await;
+    assert_eq!(res.unwrap(), Some(vec![2]));
+}
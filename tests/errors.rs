@@ -0,0 +1,109 @@
+use std::{io, pin::Pin, task::{Context, Poll}, time::Duration};
+
+use kumoko::{client::Client, server::Server, event::{Event, Origin, DisconnectEvent, ConnectionInfo, TransportKind}, transport::Listener, Error};
+use tokio::{io::{AsyncRead, AsyncWrite, DuplexStream, ReadBuf}, net::TcpListener, sync::mpsc};
+
+mod common;
+
+#[tokio::test]
+async fn full() {
+    const IP: &str = "[::1]:50079";
+    let listener = TcpListener::bind(IP).await.unwrap();
+    let client = Client::<Vec<u8>, i32>::connect(IP).await.unwrap();
+    let _stream = common::accept_stuck(&listener).await;
+
+    let mut res = Ok(());
+    for _ in 0..32 {
+        res = client.try_emit(vec![1; 4 * 1024 * 1024]);
+        if res.is_err() { break }
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    assert_eq!(res, Err(Error::Full));
+}
+
+#[tokio::test]
+async fn closed() {
+    const IP: &str = "[::1]:50080";
+    let mut server = Server::<i32, i32>::bind(IP).await.unwrap();
+    let _client = Client::<i32, i32>::connect(IP).await.unwrap();
+    let Ok((Event::Connect(_), Origin::Id(id))) = server.get_event().await else { panic!("expected a connect") };
+    let (collector, emitter) = server.into_split();
+
+    collector.shutdown(Duration::from_millis(100)).await;
+    assert_eq!(emitter.emit_response(1, id.into()).await, Err(Error::Closed));
+}
+
+/// An in-memory pipe which can be made to fail every write.
+struct Pipe{
+    inner: DuplexStream,
+    broken: bool,
+}
+
+impl AsyncRead for Pipe {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for Pipe {
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        match self.broken {
+            true => Poll::Ready(Err(io::ErrorKind::BrokenPipe.into())),
+            false => Pin::new(&mut self.inner).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+struct PipeListener(mpsc::Receiver<Pipe>);
+
+impl Listener for PipeListener {
+    type Stream = Pipe;
+
+    async fn accept(&mut self) -> io::Result<(Pipe, ConnectionInfo)> {
+        match self.0.recv().await {
+            Some(pipe) => Ok((pipe, ConnectionInfo::new(TransportKind::Custom))),
+            None => std::future::pending().await,
+        }
+    }
+}
+
+#[tokio::test]
+async fn write_failure() {
+    let (sx, rx) = mpsc::channel(1);
+    let mut server = Server::<i32, i32>::from_listener(PipeListener(rx), Default::default());
+
+    let mut clients = Vec::new();
+    for broken in [true, false] {
+        let (client_end, server_end) = tokio::io::duplex(1024);
+        sx.send(Pipe{ inner: server_end, broken }).await.unwrap();
+        let Ok((Event::Connect(_), Origin::Id(id))) = server.get_event().await else { panic!("expected a connect") };
+        clients.push((Client::<i32, i32>::from_stream(client_end), id));
+    }
+    let (_, broken) = clients.remove(0);
+    let (mut client, healthy) = clients.remove(0);
+
+    server.emit_response(1, broken.into()).await.unwrap();
+    match server.get_event().await.unwrap() {
+        (Event::Disconnect(DisconnectEvent::IoError(err)), Origin::Id(id)) => {
+            assert_eq!(id, broken);
+            assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+        },
+        e => panic!("{:?}", e),
+    }
+
+    // nobody else notices
+    server.emit_response(2, healthy.into()).await.unwrap();
+    assert_eq!(client.get_response().await.unwrap(), 2);
+    client.emit_request(3).await.unwrap();
+    let (req, origin) = server.get_request().await.unwrap();
+    assert_eq!(req, 3);
+    assert!(matches!(origin, Origin::Id(id) if id == healthy));
+}
@@ -1,3 +1,5 @@
+#![allow(clippy::match_like_matches_macro)]
+
 use kumoko::{client::Client, server::Server, event::Event::*};
 
 const IP: &str = "[::1]:50052";
@@ -8,21 +10,22 @@ async fn events() {
 
     {
         let client = Client::<i64, i64>::connect(IP).await.unwrap();
-        client.emit_request(i64::MAX).await;
+        client.emit_request(i64::MAX).await.unwrap();
     }
     {
         let client = Client::<i32, i32>::connect(IP).await.unwrap();
-        client.emit_request(11111).await;
-        client.emit_request(22222).await;
-        client.emit_request(33333).await;
+        client.emit_request(11111).await.unwrap();
+        client.emit_request(22222).await.unwrap();
+        client.emit_request(33333).await.unwrap();
     }
 
-    assert!(if let Connect        = server.get_event().await.0 {true} else {false});
-    assert!(if let Connect        = server.get_event().await.0 {true} else {false});
-    assert!(if let IllegalData(_) = server.get_event().await.0 {true} else {false});
-    assert!(if let Message(11111) = server.get_event().await.0 {true} else {false});
-    assert!(if let Disconnect(_)  = server.get_event().await.0 {true} else {false});
-    assert!(if let Message(22222) = server.get_event().await.0 {true} else {false});
-    assert!(if let Message(33333) = server.get_event().await.0 {true} else {false});
-    assert!(if let Disconnect(_)  = server.get_event().await.0 {true} else {false});
+    assert!(if let Connect(_)     = server.get_event().await.unwrap().0 {true} else {false});
+    assert!(if let Connect(_)     = server.get_event().await.unwrap().0 {true} else {false});
+    assert!(if let IllegalData(_) = server.get_event().await.unwrap().0 {true} else {false});
+    // the queued requests are written together
+    assert!(if let Message(11111) = server.get_event().await.unwrap().0 {true} else {false});
+    assert!(if let Message(22222) = server.get_event().await.unwrap().0 {true} else {false});
+    assert!(if let Message(33333) = server.get_event().await.unwrap().0 {true} else {false});
+    assert!(if let Disconnect(_)  = server.get_event().await.unwrap().0 {true} else {false});
+    assert!(if let Disconnect(_)  = server.get_event().await.unwrap().0 {true} else {false});
 }
\ No newline at end of file
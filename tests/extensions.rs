@@ -0,0 +1,37 @@
+use kumoko::{client::Client, server::Server, event::Event};
+
+#[derive(Debug, Clone, PartialEq)]
+struct User(String);
+
+#[tokio::test]
+async fn extensions() {
+    const IP: &str = "[::1]:50091";
+    let mut server = Server::<i32, i32>::bind(IP).await.unwrap();
+    let client = Client::<i32, i32>::connect(IP).await.unwrap();
+
+    let (_, origin) = server.get_event().await.unwrap();
+    let id = origin.client_id().unwrap();
+    server.insert_extension(id, User("ferris".to_string())).await.unwrap();
+    server.insert_extension(id, 0u32).await.unwrap();
+
+    for _ in 0..3 {
+        client.emit_request(1).await.unwrap();
+        let (req, origin) = server.get_request().await.unwrap();
+        let count = server.with_extensions(origin.client_id().unwrap(), move |extensions| {
+            let count = extensions.get_mut::<u32>().unwrap();
+            *count += req as u32;
+            *count
+        }).await.unwrap();
+        assert!(count.is_some());
+    }
+
+    assert_eq!(server.extension::<User>(id).await.unwrap(), Some(User("ferris".to_string())));
+    assert_eq!(server.extension::<u32>(id).await.unwrap(), Some(3));
+    assert_eq!(server.extension::<String>(id).await.unwrap(), None);
+
+    // the Extensions are gone with the Client
+    drop(client);
+    assert!(matches!(server.get_event().await.unwrap().0, Event::Disconnect(_)));
+    assert_eq!(server.extension::<User>(id).await.unwrap(), None);
+    assert_eq!(server.with_extensions(id, |_| ()).await.unwrap(), None);
+}
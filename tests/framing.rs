@@ -0,0 +1,43 @@
+use kumoko::{server::Server, event::Event::*};
+use tokio::{io::{AsyncWriteExt, AsyncReadExt}, net::TcpStream};
+
+mod common;
+use common::frame;
+
+const IP: &str = "[::1]:50053";
+
+#[tokio::test]
+async fn raw_frames() {
+    let mut server = Server::<String, String>::bind(IP).await.unwrap();
+    let mut stream = TcpStream::connect(IP).await.unwrap();
+
+    let config = bincode::config::standard();
+    let ferris = bincode::encode_to_vec("Ferris".to_string(), config).unwrap();
+
+    let mut bytes = frame(42, b"garbage of an unknown kind");
+    bytes.extend(frame(0, &[0xFF, 0xFF]));
+    bytes.extend(frame(0, &ferris));
+    // send the last frame in two pieces
+    stream.write_all(&bytes[..bytes.len() - 3]).await.unwrap();
+    stream.flush().await.unwrap();
+    tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    stream.write_all(&bytes[bytes.len() - 3..]).await.unwrap();
+
+    assert!(matches!(server.get_event().await.unwrap().0, Connect(_)));
+    assert!(matches!(server.get_event().await.unwrap().0, IllegalData(_)));
+    assert!(matches!(server.get_event().await.unwrap().0, IllegalData(_)));
+    let (req, origin) = server.get_request().await.unwrap();
+    assert_eq!(req, "Ferris");
+
+    server.emit_response("Hello Ferris".to_string(), origin.into()).await.unwrap();
+
+    let mut header = [0; 5];
+    stream.read_exact(&mut header).await.unwrap();
+    let len = u32::from_be_bytes(header[..4].try_into().unwrap()) as usize;
+    assert_eq!(header[4], 0);
+
+    let mut payload = vec![0; len];
+    stream.read_exact(&mut payload).await.unwrap();
+    let (res, _): (String, _) = bincode::decode_from_slice(&payload, config).unwrap();
+    assert_eq!(res, "Hello Ferris");
+}
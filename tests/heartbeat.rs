@@ -0,0 +1,90 @@
+use std::time::Duration;
+
+use kumoko::{client::{self, Client}, server::{self, Server}, event::{Event, DisconnectEvent}};
+use tokio::{io::AsyncReadExt, net::TcpStream};
+
+#[tokio::test]
+async fn rtt() {
+    const IP: &str = "[::1]:50068";
+    let config = server::Config{ heartbeat_interval: Some(Duration::from_millis(20)), ..Default::default() };
+    let mut server = Server::<i32, i32>::bind_with_config(IP, config).await.unwrap();
+    let config = client::Config{ heartbeat_interval: Some(Duration::from_millis(20)), ..Default::default() };
+    let mut client = Client::<i32, i32>::connect_with_config(IP, config).await.unwrap();
+
+    assert!(matches!(server.get_event().await.unwrap().0, Event::Connect(_)));
+    assert!(matches!(server.get_event().await.unwrap().0, Event::Rtt(_)));
+    assert!(matches!(client.get_event().await, Some(Event::Rtt(_))));
+
+    // heartbeats dont get in the way of messages
+    client.emit_request(1).await.unwrap();
+    assert_eq!(server.get_request().await.unwrap().0, 1);
+}
+
+#[tokio::test]
+async fn unresponsive() {
+    const IP: &str = "[::1]:50069";
+    let config = server::Config{ 
+        heartbeat_interval: Some(Duration::from_millis(20)), 
+        heartbeat_timeout: Duration::from_millis(50),
+        ..Default::default()
+    };
+    let mut server = Server::<i32, i32>::bind_with_config(IP, config).await.unwrap();
+    let mut stream = TcpStream::connect(IP).await.unwrap();
+
+    assert!(matches!(server.get_event().await.unwrap().0, Event::Connect(_)));
+
+    // a ping, which we never answer
+    let mut frame = [0; 13];
+    stream.read_exact(&mut frame).await.unwrap();
+    assert_eq!(frame[..5], [0, 0, 0, 8, 3]);
+
+    assert!(matches!(server.get_event().await.unwrap().0, Event::Disconnect(DisconnectEvent::Unresponsive)));
+}
+
+#[tokio::test]
+async fn heartbeats_dont_count_as_activity() {
+    const IP: &str = "[::1]:50096";
+    let config = server::Config{ 
+        timeout: Duration::from_millis(200),
+        heartbeat_interval: Some(Duration::from_millis(20)), 
+        ..Default::default()
+    };
+    let mut server = Server::<i32, i32>::bind_with_config(IP, config).await.unwrap();
+    // only answers the pings of the Server
+    let config = client::Config{ heartbeat_interval: None, ..Default::default() };
+    let _client = Client::<i32, i32>::connect_with_config(IP, config).await.unwrap();
+
+    let disconnect = tokio::time::timeout(Duration::from_secs(5), async {
+        loop{
+            match server.get_event().await.unwrap().0 {
+                Event::Disconnect(reason) => return reason,
+                _ => continue,
+            }
+        }
+    }).await;
+    assert!(matches!(disconnect, Ok(DisconnectEvent::Timeout)));
+}
+
+#[tokio::test]
+async fn heartbeats_dont_lose_messages() {
+    const IP: &str = "[::1]:50103";
+    let mut server = Server::<i32, i32>::bind(IP).await.unwrap();
+    // the Collector has to wait for room all the time, so heartbeats interrupt it a lot
+    let config = client::Config{ 
+        heartbeat_interval: Some(Duration::from_millis(20)), 
+        emitter_buffer: 1, 
+        ..Default::default() 
+    };
+    let mut client = Client::<i32, i32>::connect_with_config(IP, config).await.unwrap();
+
+    let (_, origin) = server.get_event().await.unwrap();
+    for i in 0..20 {
+        server.emit_response(i, origin.into()).await.unwrap();
+    }
+
+    for i in 0..20 {
+        tokio::time::sleep(Duration::from_millis(15)).await;
+        let res = tokio::time::timeout(Duration::from_secs(1), client.get_response()).await;
+        assert_eq!(res.unwrap().unwrap(), i);
+    }
+}
@@ -0,0 +1,34 @@
+use kumoko::{client::{Client, self}, server::{Server, self}, event::Event::*};
+
+#[tokio::test]
+async fn large_message() {
+    const IP: &str = "[::1]:50054";
+    let mut server = Server::<Vec<u8>, Vec<u8>>::bind(IP).await.unwrap();
+    let client = Client::<Vec<u8>, Vec<u8>>::connect(IP).await.unwrap();
+
+    let blob: Vec<u8> = (0..200_000).map(|i| i as u8).collect();
+    client.emit_request(blob.clone()).await.unwrap();
+
+    let (req, _) = server.get_request().await.unwrap();
+    assert_eq!(req, blob);
+}
+
+#[tokio::test]
+async fn oversized() {
+    const IP: &str = "[::1]:50055";
+    let config = server::Config{ max_frame_size: 1024, ..Default::default() };
+    let mut server = Server::<Vec<u8>, Vec<u8>>::bind_with_config(IP, config).await.unwrap();
+
+    let config = client::Config{ max_frame_size: 1024, ..Default::default() };
+    let client = Client::<Vec<u8>, Vec<u8>>::connect_with_config(IP, config).await.unwrap();
+
+    client.emit_request(vec![7; 4096]).await.unwrap();
+    client.emit_request(vec![1, 2, 3]).await.unwrap();
+
+    assert!(matches!(server.get_event().await.unwrap().0, Connect(_)));
+    match server.get_event().await.unwrap().0 {
+        Oversized(o) => assert_eq!(o.max, 1024),
+        e => panic!("expected Oversized, got {:?}", e),
+    }
+    assert_eq!(server.get_request().await.unwrap().0, vec![1, 2, 3]);
+}
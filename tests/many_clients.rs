@@ -8,8 +8,8 @@ async fn many() {
     create().await;
 
     loop{
-        let (req, origin): (i32, _) = server.get_request().await;
-        server.emit_response(req + 1, origin.into()).await;
+        let (req, origin): (i32, _) = server.get_request().await.unwrap();
+        server.emit_response(req + 1, origin.into()).await.unwrap();
 
         println!("sending {} to {:?}", req, origin);
 
@@ -25,7 +25,7 @@ async fn create() {
         tokio::spawn(async move{
             let mut idx = 1;
             loop{
-                client.emit_request(idx).await;
+                client.emit_request(idx).await.unwrap();
                 idx = client.get_response().await.unwrap();
                 tokio::task::yield_now().await;
             }
@@ -0,0 +1,73 @@
+use kumoko::{client::Client, server::Server, event::Event, Error};
+use tokio::{io::AsyncWriteExt, net::TcpStream};
+
+mod common;
+use common::frame;
+
+async fn expect_subscription(server: &mut Server<i32, String>, expected: &str) {
+    match server.get_event().await.unwrap().0 {
+        Event::Subscribe(pattern) | Event::Unsubscribe(pattern) => assert_eq!(pattern, expected),
+        e => panic!("{:?}", e),
+    }
+}
+
+#[tokio::test]
+async fn publish() {
+    const IP: &str = "[::1]:50089";
+    let mut server = Server::<i32, String>::bind(IP).await.unwrap();
+
+    let mut wildcard = Client::<i32, String>::connect(IP).await.unwrap();
+    assert!(matches!(server.get_event().await.unwrap().0, Event::Connect(_)));
+    let mut both = Client::<i32, String>::connect(IP).await.unwrap();
+    assert!(matches!(server.get_event().await.unwrap().0, Event::Connect(_)));
+
+    wildcard.subscribe("match.*.score").await.unwrap();
+    expect_subscription(&mut server, "match.*.score").await;
+    both.subscribe("match.1.score").await.unwrap();
+    expect_subscription(&mut server, "match.1.score").await;
+    both.subscribe("match.*.score").await.unwrap();
+    expect_subscription(&mut server, "match.*.score").await;
+
+    server.publish("match.1.score", "1:0".to_string()).await.unwrap();
+    server.publish("match.2.score", "2:2".to_string()).await.unwrap();
+    // neither matches a pattern
+    server.publish("match.1.goals", "nope".to_string()).await.unwrap();
+    server.publish("match.1.2.score", "nope".to_string()).await.unwrap();
+    server.broadcast("end".to_string()).await.unwrap();
+
+    for client in [&mut wildcard, &mut both] {
+        for expected in ["1:0", "2:2", "end"] {
+            assert_eq!(client.get_response().await.unwrap(), expected);
+        }
+    }
+
+    both.unsubscribe("match.*.score").await.unwrap();
+    expect_subscription(&mut server, "match.*.score").await;
+    server.publish("match.2.score", "3:2".to_string()).await.unwrap();
+    server.broadcast("end".to_string()).await.unwrap();
+
+    assert_eq!(wildcard.get_response().await.unwrap(), "3:2");
+    assert_eq!(wildcard.get_response().await.unwrap(), "end");
+    assert_eq!(both.get_response().await.unwrap(), "end");
+}
+
+#[tokio::test]
+async fn invalid_patterns() {
+    const IP: &str = "[::1]:50106";
+    let mut server = Server::<i32, String>::bind(IP).await.unwrap();
+    let client = Client::<i32, String>::connect(IP).await.unwrap();
+    assert!(matches!(server.get_event().await.unwrap().0, Event::Connect(_)));
+
+    for pattern in ["", "match..score", "match.**", "match.1*.score"] {
+        assert_eq!(client.subscribe(pattern).await, Err(Error::InvalidPattern));
+        assert_eq!(client.unsubscribe(pattern).await, Err(Error::InvalidPattern));
+    }
+
+    // a peer which doesnt check them only loses that subscription
+    let mut stream = TcpStream::connect(IP).await.unwrap();
+    assert!(matches!(server.get_event().await.unwrap().0, Event::Connect(_)));
+    stream.write_all(&frame(6, b"match.**")).await.unwrap();
+    stream.write_all(&frame(6, b"match.*.score")).await.unwrap();
+    assert!(matches!(server.get_event().await.unwrap().0, Event::IllegalData(_)));
+    expect_subscription(&mut server, "match.*.score").await;
+}
@@ -0,0 +1,134 @@
+use std::time::Duration;
+
+use kumoko::{client::{self, Client, Reconnect}, server::Server, event::{Event, DisconnectEvent}};
+use tokio::net::TcpListener;
+
+fn config(max_attempts: Option<u32>) -> client::Config {
+    let reconnect = Reconnect{ initial_delay: Duration::from_millis(20), max_attempts, ..Default::default() };
+    client::Config{ reconnect: Some(reconnect), ..Default::default() }
+}
+
+#[tokio::test]
+async fn server_restart() {
+    const IP: &str = "[::1]:50092";
+    let mut server = Server::<i32, i32>::bind(IP).await.unwrap();
+    let mut client = Client::<i32, i32>::connect_with_config(IP, config(None)).await.unwrap();
+    assert!(matches!(server.get_event().await.unwrap().0, Event::Connect(_)));
+
+    server.shutdown(Duration::from_millis(100)).await;
+    assert!(matches!(client.get_event().await, Some(Event::Disconnect(DisconnectEvent::ServerShutdown))));
+    assert!(matches!(client.get_event().await, Some(Event::Reconnecting{ attempt: 1, .. })));
+
+    // buffered until the Server is back
+    client.emit_request(1).await.unwrap();
+    client.emit_request(2).await.unwrap();
+
+    let mut server = Server::<i32, i32>::bind(IP).await.unwrap();
+    loop{
+        match client.get_event().await {
+            Some(Event::Reconnecting{ .. }) => continue,
+            Some(Event::Reconnected) => break,
+            e => panic!("{:?}", e),
+        }
+    }
+
+    assert!(matches!(server.get_event().await.unwrap().0, Event::Connect(_)));
+    for expected in [1, 2] {
+        let (req, origin) = server.get_request().await.unwrap();
+        assert_eq!(req, expected);
+        server.emit_response(req * 10, origin.into()).await.unwrap();
+        assert_eq!(client.get_response().await, Some(expected * 10));
+    }
+
+    // calls work again too
+    let call = tokio::spawn(async move{ client.call(3).await });
+    let (req, origin) = server.get_request().await.unwrap();
+    server.emit_response(req * 10, origin.into()).await.unwrap();
+    assert_eq!(call.await.unwrap().unwrap(), 30);
+}
+
+#[tokio::test]
+async fn kicked() {
+    const IP: &str = "[::1]:50093";
+    let mut server = Server::<i32, i32>::bind(IP).await.unwrap();
+    let mut client = Client::<i32, i32>::connect_with_config(IP, config(None)).await.unwrap();
+    let (_, origin) = server.get_event().await.unwrap();
+
+    server.disconnect(origin.into(), "go away").await.unwrap();
+    assert!(matches!(client.get_event().await, Some(Event::Disconnect(DisconnectEvent::KickedByServer{ .. }))));
+    assert!(client.get_event().await.is_none());
+}
+
+#[tokio::test]
+async fn gives_up() {
+    const IP: &str = "[::1]:50094";
+    let mut server = Server::<i32, i32>::bind(IP).await.unwrap();
+    let mut client = Client::<i32, i32>::connect_with_config(IP, config(Some(2))).await.unwrap();
+    assert!(matches!(server.get_event().await.unwrap().0, Event::Connect(_)));
+    server.shutdown(Duration::from_millis(100)).await;
+
+    assert!(matches!(client.get_event().await, Some(Event::Disconnect(_))));
+    assert!(matches!(client.get_event().await, Some(Event::Reconnecting{ attempt: 1, .. })));
+    assert!(matches!(client.get_event().await, Some(Event::Reconnecting{ attempt: 2, .. })));
+    assert!(client.get_event().await.is_none());
+}
+
+#[tokio::test]
+async fn queued_requests_survive() {
+    const IP: &str = "[::1]:50101";
+    const BIG: usize = 256 * 1024;
+    // a Server which never reads, so the Requests pile up in the Client
+    let listener = TcpListener::bind(IP).await.unwrap();
+    let config = client::Config{ collector_buffer: 64, ..config(None) };
+    let mut client = Client::<Vec<u8>, i32>::connect_with_config(IP, config).await.unwrap();
+    let (stream, _) = listener.accept().await.unwrap();
+
+    for i in 0..96 {
+        client.emit_request(vec![i; BIG]).await.unwrap();
+    }
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    drop((stream, listener));
+
+    assert!(matches!(client.get_event().await, Some(Event::Disconnect(_))));
+    let mut server = Server::<Vec<u8>, i32>::bind(IP).await.unwrap();
+    assert!(matches!(server.get_event().await.unwrap().0, Event::Connect(_)));
+
+    // only what was stuck in the socket is lost, not the 64 Requests queued for it
+    let (first, _) = server.get_request().await.unwrap();
+    assert!(first[0] < 64, "{}", first[0]);
+    for expected in first[0] + 1..96 {
+        let (req, _) = server.get_request().await.unwrap();
+        assert_eq!(req[0], expected);
+    }
+}
+
+#[tokio::test]
+async fn subscriptions() {
+    const IP: &str = "[::1]:50107";
+    let mut server = Server::<i32, i32>::bind(IP).await.unwrap();
+    let mut client = Client::<i32, i32>::connect_with_config(IP, config(None)).await.unwrap();
+    assert!(matches!(server.get_event().await.unwrap().0, Event::Connect(_)));
+
+    client.subscribe("match.*.score").await.unwrap();
+    client.subscribe("match.1.goals").await.unwrap();
+    client.unsubscribe("match.1.goals").await.unwrap();
+    for _ in 0..3 {
+        assert!(matches!(server.get_event().await.unwrap().0, Event::Subscribe(_) | Event::Unsubscribe(_)));
+    }
+
+    server.shutdown(Duration::from_millis(100)).await;
+    let mut server = Server::<i32, i32>::bind(IP).await.unwrap();
+    loop{
+        match client.get_event().await {
+            Some(Event::Reconnected) => break,
+            Some(_) => continue,
+            None => panic!("the Client gave up"),
+        }
+    }
+
+    // only what is still subscribed, before the Requests
+    client.emit_request(1).await.unwrap();
+    assert!(matches!(server.get_event().await.unwrap().0, Event::Connect(_)));
+    assert!(matches!(server.get_event().await.unwrap().0, Event::Subscribe(pattern) if pattern == "match.*.score"));
+    assert!(matches!(server.get_event().await.unwrap().0, Event::Message(1)));
+}
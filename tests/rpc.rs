@@ -0,0 +1,92 @@
+use std::time::Duration;
+
+use kumoko::{client::{self, Client, RpcError}, server::{self, Server, Target}, event::{Event, Origin}};
+use tokio::net::TcpListener;
+
+mod common;
+use common::Picky;
+
+#[tokio::test]
+async fn out_of_order() {
+    const IP: &str = "[::1]:50065";
+    let mut server = Server::<i32, i32>::bind(IP).await.unwrap();
+    let client = Client::<i32, i32>::connect(IP).await.unwrap();
+
+    tokio::spawn(async move{
+        let (first, first_origin) = server.get_request().await.unwrap();
+        let (second, second_origin) = server.get_request().await.unwrap();
+        assert!(matches!(first_origin, Origin::Call(..)));
+
+        server.emit_response(second * 10, second_origin.into()).await.unwrap();
+        server.emit_response(first * 10, first_origin.into()).await.unwrap();
+    });
+
+    let (a, b) = tokio::join!(client.call(1), client.call(2));
+    assert_eq!(a.unwrap(), 10);
+    assert_eq!(b.unwrap(), 20);
+}
+
+#[tokio::test]
+async fn timeout() {
+    const IP: &str = "[::1]:50066";
+    let mut server = Server::<i32, i32>::bind(IP).await.unwrap();
+    let mut client = Client::<i32, i32>::connect(IP).await.unwrap();
+
+    let res = client.call_with_timeout(1, Duration::from_millis(50)).await;
+    assert!(matches!(res, Err(RpcError::Timeout)));
+
+    // a late reply is dropped instead of showing up as an event
+    let (_, origin) = server.get_request().await.unwrap();
+    server.emit_response(10, origin.into()).await.unwrap();
+    let Origin::Call(id, _) = origin else { panic!("expected a call") };
+    server.emit_response(30, Target::One(id)).await.unwrap();
+
+    assert_eq!(client.get_response().await.unwrap(), 30);
+}
+
+#[tokio::test]
+async fn disconnected() {
+    const IP: &str = "[::1]:50067";
+    let listener = TcpListener::bind(IP).await.unwrap();
+    let client = Client::<i32, i32>::connect(IP).await.unwrap();
+
+    let call = tokio::spawn(async move{ client.call(1).await });
+    let (stream, _) = listener.accept().await.unwrap();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    drop(stream);
+
+    assert!(matches!(call.await.unwrap(), Err(RpcError::Disconnected)));
+}
+
+#[tokio::test]
+async fn unencodable() {
+    const IP: &str = "[::1]:50102";
+    let config = server::Config::<Picky>::default();
+    let mut server = Server::<i32, i32, Picky>::bind_with_config(IP, config).await.unwrap();
+    let config = client::Config::<Picky>::default();
+    let mut client = Client::<i32, i32, Picky>::connect_with_config(IP, config).await.unwrap();
+
+    // the request never leaves the Client
+    let res = client.call(-1).await;
+    assert!(matches!(res, Err(RpcError::Failed(_))));
+    client.emit_request(-1).await.unwrap();
+    assert!(matches!(client.get_event().await, Some(Event::EncodeError(_))));
+
+    // neither does the response, but the call still fails right away
+    let call = tokio::spawn(async move{ (client.call(1).await, client) });
+    let (_, origin) = server.get_request().await.unwrap();
+    server.emit_response(-1, origin.into()).await.unwrap();
+    let (res, mut client) = call.await.unwrap();
+    assert!(matches!(res, Err(RpcError::Failed(_))));
+
+    let Origin::Call(id, _) = origin else { panic!("expected a call") };
+    server.emit_response(-1, Target::One(id)).await.unwrap();
+    let (event, _) = server.get_event().await.unwrap();
+    assert!(matches!(event, Event::EncodeError(_)));
+
+    // the connection survives all of that
+    client.emit_request(2).await.unwrap();
+    let (req, origin) = server.get_request().await.unwrap();
+    server.emit_response(req * 10, origin.into()).await.unwrap();
+    assert_eq!(client.get_response().await.unwrap(), 20);
+}
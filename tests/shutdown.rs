@@ -0,0 +1,64 @@
+use std::time::Duration;
+
+use kumoko::{client::Client, server::{Config, Server, ShutdownReport}, event::{Event, DisconnectEvent}};
+use tokio::net::TcpStream;
+
+mod common;
+
+#[tokio::test]
+async fn graceful() {
+    const IP: &str = "[::1]:50077";
+    let mut server = Server::<i32, i32>::bind(IP).await.unwrap();
+    let mut first = Client::<i32, i32>::connect(IP).await.unwrap();
+    let mut second = Client::<i32, i32>::connect(IP).await.unwrap();
+
+    first.emit_request(1).await.unwrap();
+    let (_, origin) = server.get_request().await.unwrap();
+    server.emit_response(10, origin.into()).await.unwrap();
+
+    let report = server.shutdown(Duration::from_secs(1)).await;
+    assert_eq!(report, ShutdownReport{ clients: Some(2), dropped_requests: 0, aborted: 0 });
+
+    // queued responses are drained before the close frame
+    assert_eq!(first.get_response().await, Some(10));
+    for client in [&mut first, &mut second] {
+        assert!(matches!(client.get_event().await, Some(Event::Disconnect(DisconnectEvent::ServerShutdown))));
+        assert!(client.get_event().await.is_none());
+    }
+
+    assert!(TcpStream::connect(IP).await.is_err());
+}
+
+#[tokio::test]
+async fn aborted() {
+    const IP: &str = "[::1]:50078";
+    let mut server = Server::<i32, Vec<u8>>::bind(IP).await.unwrap();
+    let _stream = common::connect_stuck(IP).await;
+
+    let (_, origin) = server.get_event().await.unwrap();
+    for _ in 0..8 {
+        server.emit_response(vec![1; 4 * 1024 * 1024], origin.into()).await.unwrap();
+    }
+
+    let report = server.shutdown(Duration::from_millis(100)).await;
+    assert_eq!(report.aborted, 1);
+}
+
+#[tokio::test]
+async fn stuck_pool() {
+    const IP: &str = "[::1]:50098";
+    let config = Config{ client_buffer: 1, pool_buffer: 1, heartbeat_interval: None, ..Default::default() };
+    let server = Server::<i32, Vec<u8>>::bind_with_config(IP, config).await.unwrap();
+    // and once its buffer is full, the pool gets stuck too
+    let _stream = common::connect_stuck(IP).await;
+
+    let (mut collector, emitter) = server.into_split();
+    let (_, origin) = collector.get_event().await.unwrap();
+    tokio::spawn(async move{
+        while emitter.emit_response(vec![1; 4 * 1024 * 1024], origin.into()).await.is_ok() {}
+    });
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    let report = tokio::time::timeout(Duration::from_secs(5), collector.shutdown(Duration::from_millis(100))).await;
+    assert!(matches!(report, Ok(ShutdownReport{ clients: None, aborted: 1, .. })));
+}
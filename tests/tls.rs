@@ -0,0 +1,90 @@
+use std::{io::Write, sync::Arc, time::Duration};
+
+use kumoko::{client::Client, server::Server, event::{DisconnectEvent, Event::*}, rustls};
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
+use tokio::{io::AsyncReadExt, net::TcpStream};
+
+const IP: &str = "[::1]:50056";
+
+fn tls_configs() -> (Arc<rustls::ServerConfig>, Arc<rustls::ClientConfig>) {
+    let cert = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+    let der = CertificateDer::from(cert.cert.der().to_vec());
+    let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(cert.key_pair.serialize_der()));
+
+    let server = rustls::ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(vec![der.clone()], key)
+        .unwrap();
+
+    let mut roots = rustls::RootCertStore::empty();
+    roots.add(der).unwrap();
+    let client = rustls::ClientConfig::builder()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+
+    (Arc::new(server), Arc::new(client))
+}
+
+#[tokio::test]
+async fn tls() {
+    let (server_tls, client_tls) = tls_configs();
+    let mut server = Server::<i32, i32>::bind_tls(IP, Default::default(), server_tls).await.unwrap();
+    let mut client = Client::connect_tls(IP, "localhost", client_tls).await.unwrap();
+
+    client.emit_request(15).await.unwrap();
+
+    assert!(matches!(server.get_event().await.unwrap().0, Connect(_)));
+    let (req, origin) = server.get_request().await.unwrap();
+    server.emit_response(req + 4, origin.into()).await.unwrap();
+
+    let res: i32 = client.get_response().await.unwrap();
+    assert_eq!(res, 19);
+
+    drop(client);
+    assert!(matches!(server.get_event().await.unwrap().0, Disconnect(_)));
+}
+
+#[tokio::test]
+async fn wrong_domain() {
+    const IP: &str = "[::1]:50057";
+    let (server_tls, client_tls) = tls_configs();
+    let _server = Server::<i32, i32>::bind_tls(IP, Default::default(), server_tls).await.unwrap();
+
+    assert!(Client::<i32, i32>::connect_tls(IP, "ferris.rs", client_tls).await.is_err());
+}
+
+#[tokio::test]
+async fn handshake_timeout() {
+    const IP: &str = "[::1]:50095";
+    let (server_tls, _) = tls_configs();
+    let config = kumoko::server::Config{ handshake_timeout: Duration::from_millis(50), ..Default::default() };
+    let _server = Server::<i32, i32>::bind_tls(IP, config, server_tls).await.unwrap();
+
+    // never send a ClientHello
+    let mut stream = TcpStream::connect(IP).await.unwrap();
+    let read = tokio::time::timeout(Duration::from_secs(5), stream.read(&mut [0; 16])).await;
+    assert!(matches!(read, Ok(Ok(0))));
+}
+
+#[tokio::test]
+async fn corrupt_records() {
+    const IP: &str = "[::1]:50097";
+    let (server_tls, client_tls) = tls_configs();
+    let mut server = Server::<i32, i32>::bind_tls(IP, Default::default(), server_tls).await.unwrap();
+
+    let _stream = tokio::task::spawn_blocking(move || {
+        let mut stream = std::net::TcpStream::connect(IP).unwrap();
+        let name = rustls::pki_types::ServerName::try_from("localhost").unwrap();
+        let mut conn = rustls::ClientConnection::new(client_tls, name).unwrap();
+        while conn.is_handshaking() {
+            conn.complete_io(&mut stream).unwrap();
+        }
+        // a record of an unknown content type, written past rustls
+        stream.write_all(&[0x99, 0x03, 0x03, 0x00, 0x01, 0x00]).unwrap();
+        stream
+    }).await.unwrap();
+
+    assert!(matches!(server.get_event().await.unwrap().0, Connect(_)));
+    let event = tokio::time::timeout(Duration::from_secs(5), server.get_event()).await;
+    assert!(matches!(event, Ok(Ok((Disconnect(DisconnectEvent::IoError(_)), _)))));
+}
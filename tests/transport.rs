@@ -0,0 +1,63 @@
+use std::{io, time::SystemTime};
+
+use kumoko::{client::Client, server::Server, event::{Event::*, Origin, ConnectionInfo, TransportKind}, transport::Listener};
+use tokio::{io::DuplexStream, sync::mpsc};
+
+/// Hands out in-memory pipes instead of sockets.
+struct DuplexListener(mpsc::Receiver<DuplexStream>);
+
+impl Listener for DuplexListener {
+    type Stream = DuplexStream;
+
+    async fn accept(&mut self) -> io::Result<(DuplexStream, ConnectionInfo)> {
+        match self.0.recv().await {
+            Some(stream) => Ok((stream, ConnectionInfo::new(TransportKind::Custom))),
+            None => std::future::pending().await,
+        }
+    }
+}
+
+#[tokio::test]
+async fn duplex() {
+    let (sx, rx) = mpsc::channel(1);
+    let mut server = Server::<i32, i32>::from_listener(DuplexListener(rx), Default::default());
+
+    let (client_end, server_end) = tokio::io::duplex(1024);
+    sx.send(server_end).await.unwrap();
+    let mut client = Client::from_stream(client_end);
+
+    client.emit_request(15).await.unwrap();
+
+    assert!(matches!(server.get_event().await.unwrap().0, Connect(_)));
+    let (req, origin) = server.get_request().await.unwrap();
+    server.emit_response(req + 4, origin.into()).await.unwrap();
+
+    let res: i32 = client.get_response().await.unwrap();
+    assert_eq!(res, 19);
+}
+
+#[tokio::test]
+async fn connection_info() {
+    const IP: &str = "[::1]:50090";
+    let mut server = Server::<i32, i32>::bind(IP).await.unwrap();
+    let client = Client::<i32, i32>::connect(IP).await.unwrap();
+
+    let (info, origin) = match server.get_event().await.unwrap() {
+        (Connect(info), Origin::Id(id)) => (info, id),
+        e => panic!("{:?}", e),
+    };
+    assert_eq!(info.transport, TransportKind::Tcp);
+    assert_eq!(info.local_addr, Some(IP.parse().unwrap()));
+    assert!(info.peer_addr.unwrap().ip().is_loopback());
+    assert!(info.connected_at <= SystemTime::now());
+
+    assert_eq!(server.connection_info(origin).await.unwrap(), Some(info.clone()));
+    assert_eq!(server.clients().await.unwrap(), vec![(origin, info)]);
+    assert_eq!(server.connection_count().await.unwrap(), 1);
+
+    drop(client);
+    assert!(matches!(server.get_event().await.unwrap().0, Disconnect(_)));
+    assert_eq!(server.connection_info(origin).await.unwrap(), None);
+    assert_eq!(server.clients().await.unwrap(), vec![]);
+    assert_eq!(server.connection_count().await.unwrap(), 0);
+}
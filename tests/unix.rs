@@ -0,0 +1,30 @@
+#![cfg(unix)]
+
+use kumoko::{client::Client, server::Server, event::Event::*};
+
+#[tokio::test]
+async fn unix() {
+    let path = std::env::temp_dir().join(format!("kumoko-{}.sock", std::process::id()));
+    std::fs::remove_file(&path).ok();
+
+    let mut server = Server::<i32, i32>::bind_unix(&path).await.unwrap();
+    let mut client = Client::connect_unix(&path).await.unwrap();
+
+    client.emit_request(15).await.unwrap();
+
+    match server.get_event().await.unwrap().0 {
+        Connect(info) => {
+            let cred = info.credentials.expect("unix sockets have credentials");
+            assert_eq!(cred.pid, Some(std::process::id() as i32));
+        },
+        e => panic!("expected Connect, got {:?}", e),
+    }
+
+    let (req, origin) = server.get_request().await.unwrap();
+    server.emit_response(req + 4, origin.into()).await.unwrap();
+
+    let res: i32 = client.get_response().await.unwrap();
+    assert_eq!(res, 19);
+
+    std::fs::remove_file(&path).ok();
+}
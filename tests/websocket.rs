@@ -0,0 +1,39 @@
+use futures_util::SinkExt;
+use kumoko::{client::Client, server::Server, event::Event::*};
+use tokio_tungstenite::tungstenite::Message;
+
+#[tokio::test]
+async fn websocket() {
+    const IP: &str = "[::1]:50058";
+    let mut server = Server::<i32, i32>::bind_ws(IP, Default::default()).await.unwrap();
+    let mut client = Client::connect_ws("ws://[::1]:50058").await.unwrap();
+
+    client.emit_request(15).await.unwrap();
+
+    assert!(matches!(server.get_event().await.unwrap().0, Connect(_)));
+    let (req, origin) = server.get_request().await.unwrap();
+    server.emit_response(req + 4, origin.into()).await.unwrap();
+
+    let res: i32 = client.get_response().await.unwrap();
+    assert_eq!(res, 19);
+
+    drop(client);
+    assert!(matches!(server.get_event().await.unwrap().0, Disconnect(_)));
+}
+
+#[tokio::test]
+async fn raw_websocket() {
+    const IP: &str = "[::1]:50059";
+    let mut server = Server::<i32, i32>::bind_ws(IP, Default::default()).await.unwrap();
+    let (mut ws, _) = tokio_tungstenite::connect_async("ws://[::1]:50059").await.unwrap();
+
+    // a frame of an unknown kind, then a valid frame carrying 7
+    ws.send(Message::binary(vec![0, 0, 0, 1, 42, 0])).await.unwrap();
+    ws.send(Message::binary(vec![0, 0, 0, 1, 0, 14])).await.unwrap();
+    ws.close(None).await.unwrap();
+
+    assert!(matches!(server.get_event().await.unwrap().0, Connect(_)));
+    assert!(matches!(server.get_event().await.unwrap().0, IllegalData(_)));
+    assert!(matches!(server.get_event().await.unwrap().0, Message(7)));
+    assert!(matches!(server.get_event().await.unwrap().0, Disconnect(_)));
+}